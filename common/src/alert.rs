@@ -0,0 +1,20 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::Nag;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Alert //////////////////////////////////////////////////////////////////////
+
+/// A nag that has fired and is still waiting to be dismissed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Alert {
+    pub nag: Nag,
+    pub fired_at: DateTime<Utc>,
+    /// how many times the nag has been re-shown so far
+    pub escalations: u32,
+}
@@ -0,0 +1,215 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+//! The socket protocol without tokio, for small synchronous tools.
+
+use crate::alert::Alert;
+use crate::client_error::{into_result, ClientError, Failure};
+use crate::command::Command;
+use crate::filter::NagFilter;
+use crate::history::{HistoryEvent, HistoryFilter};
+use crate::nag::Nag;
+use crate::response::Response;
+use crate::{COMSOCK_PATH, MAX_MESSAGE_LEN};
+
+use log::debug;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+// framing ////////////////////////////////////////////////////////////////////
+
+pub fn send_message<W: Write>(
+    write_stream: &mut W,
+    msg: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    write_stream.write_all(&(msg.len() as u32).to_le_bytes())?;
+    debug!("sending message ({})...", msg);
+    write_stream.write_all(msg.as_bytes())?;
+    write_stream.flush()?;
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn recv_message<R: Read>(
+    reader: &mut R,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut size = [0; 4];
+    reader.read_exact(&mut size)?;
+    let size = u32::from_le_bytes(size);
+    if size > MAX_MESSAGE_LEN {
+        return Err(Box::new(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes is over the limit", size),
+        )));
+    }
+
+    let mut buf = vec![0; size as usize];
+    reader.read_exact(&mut buf)?;
+
+    Ok(String::from_utf8(buf)?)
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn send_command<W: Write>(
+    write_stream: &mut W,
+    command: &Command,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    send_message(write_stream, &serde_json::to_string(command)?)
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn recv_response<R: Read>(
+    reader: &mut R,
+) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+    let message = recv_message(reader)?;
+    Ok(serde_json::from_str(&message)?)
+}
+
+// Client /////////////////////////////////////////////////////////////////////
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Blocking counterpart of `crate::Client`, talking to the local socket only.
+pub struct Client {
+    path: String,
+    timeout: Duration,
+    stream: Option<UnixStream>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Client {
+    #[must_use]
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            timeout: DEFAULT_TIMEOUT,
+            stream: None,
+        }
+    }
+
+    /// A client for the local nagd socket.
+    #[must_use]
+    pub fn local() -> Self {
+        Self::new(COMSOCK_PATH)
+    }
+
+    /// How long nagd may take to answer before a request gives up.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn connect(&self) -> Result<UnixStream, ClientError> {
+        debug!("Connecting to {}", self.path);
+        let stream =
+            UnixStream::connect(&self.path).map_err(|err| ClientError::Transport(err.into()))?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            .map_err(|err| ClientError::Transport(err.into()))?;
+        Ok(stream)
+    }
+
+    /// Sends any command, nagd's errors come back as `ClientError::Daemon`.
+    pub fn request(&mut self, command: Command) -> Result<Response, ClientError> {
+        // a kept connection goes stale when nagd restarts, the command is sent
+        // again on a fresh one when that can't run it twice
+        if let Some(mut stream) = self.stream.take() {
+            match exchange(&mut stream, &command) {
+                Ok(response) => {
+                    self.stream = Some(stream);
+                    return into_result(response);
+                }
+                Err(failure) if failure.can_resend(&command) => {
+                    debug!("Reconnecting after {}", failure.err);
+                }
+                Err(failure) => return Err(transport_error(failure.err)),
+            }
+        }
+
+        let mut stream = self.connect()?;
+        let response =
+            exchange(&mut stream, &command).map_err(|failure| transport_error(failure.err))?;
+        self.stream = Some(stream);
+        into_result(response)
+    }
+
+    pub fn add(&mut self, nag: Nag) -> Result<(), ClientError> {
+        match self.request(Command::AddNag { nag })? {
+            Response::Ok => Ok(()),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    pub fn list(&mut self, filter: NagFilter) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::ListNags { filter })
+    }
+
+    /// Replaces every nag, nags with id 0 are given a new one.
+    pub fn set(&mut self, nags: Vec<Nag>) -> Result<(), ClientError> {
+        match self.request(Command::SetNags { nags })? {
+            Response::Ok => Ok(()),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    /// Returns the nags that were removed.
+    pub fn remove(&mut self, filter: NagFilter) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::RemoveNags { filter })
+    }
+
+    /// Returns the nags that were removed.
+    pub fn clear(&mut self) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::ClearNags)
+    }
+
+    pub fn alerts(&mut self) -> Result<Vec<Alert>, ClientError> {
+        match self.request(Command::ListAlerts)? {
+            Response::AlertList { alerts } => Ok(alerts),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    pub fn history(&mut self, filter: HistoryFilter) -> Result<Vec<HistoryEvent>, ClientError> {
+        match self.request(Command::ListHistory { filter })? {
+            Response::History { events } => Ok(events),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    fn request_nags(&mut self, command: Command) -> Result<Vec<Nag>, ClientError> {
+        match self.request(command)? {
+            Response::NagList { nags } => Ok(nags),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn exchange(stream: &mut UnixStream, command: &Command) -> Result<Response, Failure> {
+    send_command(stream, command).map_err(|err| Failure { sent: false, err })?;
+    recv_response(stream).map_err(|err| Failure { sent: true, err })
+}
+
+// ----------------------------------------------------------------------------
+
+fn transport_error(err: Box<dyn std::error::Error + Send + Sync>) -> ClientError {
+    // socket timeouts surface as WouldBlock or TimedOut depending on platform
+    match err
+        .downcast_ref::<std::io::Error>()
+        .map(std::io::Error::kind)
+    {
+        Some(std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => ClientError::Timeout,
+        _ => ClientError::Transport(err),
+    }
+}
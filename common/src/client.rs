@@ -0,0 +1,264 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::alert::Alert;
+use crate::client_error::{into_result, ClientError, Failure};
+use crate::command::{Command, NagEdit};
+use crate::filter::NagFilter;
+use crate::history::{HistoryEvent, HistoryFilter};
+use crate::nag::Nag;
+use crate::quiet::QuietStatus;
+use crate::recv::recv_response;
+use crate::remote::{connect_tcp, split_boxed, BoxedReader, BoxedWriter};
+use crate::response::Response;
+use crate::routine::RoutineStatus;
+use crate::send::send_command;
+use crate::COMSOCK_PATH;
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::collections::BTreeMap;
+use tokio::net::UnixStream;
+use tokio::time::{timeout, Duration};
+
+// Endpoint ///////////////////////////////////////////////////////////////////
+
+/// Where a `Client` finds nagd.
+#[derive(Clone, Debug)]
+pub enum Endpoint {
+    /// path of nagd's unix socket
+    Local(String),
+    /// a nagd TCP listener, `tls_ca` is the certificate to trust over TLS
+    Remote {
+        addr: String,
+        token: String,
+        tls_ca: Option<String>,
+    },
+}
+
+// Client /////////////////////////////////////////////////////////////////////
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Talks to nagd, keeping one connection open across requests.
+pub struct Client {
+    endpoint: Endpoint,
+    timeout: Duration,
+    connection: Option<(BoxedReader, BoxedWriter)>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Client {
+    #[must_use]
+    pub fn new(endpoint: Endpoint) -> Self {
+        Self {
+            endpoint,
+            timeout: DEFAULT_TIMEOUT,
+            connection: None,
+        }
+    }
+
+    /// A client for the local nagd socket.
+    #[must_use]
+    pub fn local() -> Self {
+        Self::new(Endpoint::Local(COMSOCK_PATH.to_string()))
+    }
+
+    /// How long connecting, including any TLS and token handshake, may take.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn connect(&self) -> Result<(BoxedReader, BoxedWriter), ClientError> {
+        debug!("Connecting to {:?}", self.endpoint);
+        let connecting = async {
+            match &self.endpoint {
+                Endpoint::Local(path) => Ok(split_boxed(UnixStream::connect(path).await?)),
+                Endpoint::Remote {
+                    addr,
+                    token,
+                    tls_ca,
+                } => connect_tcp(addr, token, tls_ca.as_deref()).await,
+            }
+        };
+
+        timeout(self.timeout, connecting)
+            .await
+            .map_err(|_| ClientError::Timeout)?
+            .map_err(ClientError::Transport)
+    }
+
+    /// Sends any command, nagd's errors come back as `ClientError::Daemon`.
+    pub async fn request(&mut self, command: Command) -> Result<Response, ClientError> {
+        // a kept connection goes stale when nagd restarts, the command is sent
+        // again on a fresh one when that can't run it twice
+        if let Some(mut connection) = self.connection.take() {
+            match exchange(&mut connection, command.clone()).await {
+                Ok(response) => {
+                    self.connection = Some(connection);
+                    return into_result(response);
+                }
+                Err(failure) if failure.can_resend(&command) => {
+                    debug!("Reconnecting after {}", failure.err);
+                }
+                Err(failure) => return Err(ClientError::Transport(failure.err)),
+            }
+        }
+
+        let mut connection = self.connect().await?;
+        let response = exchange(&mut connection, command)
+            .await
+            .map_err(|failure| ClientError::Transport(failure.err))?;
+        self.connection = Some(connection);
+        into_result(response)
+    }
+
+    pub async fn add(&mut self, nag: Nag) -> Result<(), ClientError> {
+        self.request_ok(Command::AddNag { nag }).await
+    }
+
+    pub async fn list(&mut self, filter: NagFilter) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::ListNags { filter }).await
+    }
+
+    /// Replaces every nag, nags with id 0 are given a new one.
+    pub async fn set(&mut self, nags: Vec<Nag>) -> Result<(), ClientError> {
+        self.request_ok(Command::SetNags { nags }).await
+    }
+
+    /// Returns the nags that were removed.
+    pub async fn remove(&mut self, filter: NagFilter) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::RemoveNags { filter }).await
+    }
+
+    /// Returns the nag as it is after the edit.
+    pub async fn edit(&mut self, id: u64, edit: NagEdit) -> Result<Nag, ClientError> {
+        let mut nags = self.request_nags(Command::EditNag { id, edit }).await?;
+        nags.pop()
+            .ok_or(ClientError::Unexpected(Response::NagList { nags }))
+    }
+
+    /// Returns the nags that were removed.
+    pub async fn clear(&mut self) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::ClearNags).await
+    }
+
+    /// Returns the nags that were paused, every nag with an empty filter.
+    pub async fn pause(&mut self, filter: NagFilter) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::PauseNags { filter }).await
+    }
+
+    /// Returns the nags that were resumed, every paused nag with an empty
+    /// filter.
+    pub async fn resume(&mut self, filter: NagFilter) -> Result<Vec<Nag>, ClientError> {
+        self.request_nags(Command::ResumeNags { filter }).await
+    }
+
+    pub async fn alerts(&mut self) -> Result<Vec<Alert>, ClientError> {
+        match self.request(Command::ListAlerts).await? {
+            Response::AlertList { alerts } => Ok(alerts),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    pub async fn history(
+        &mut self,
+        filter: HistoryFilter,
+    ) -> Result<Vec<HistoryEvent>, ClientError> {
+        match self.request(Command::ListHistory { filter }).await? {
+            Response::History { events } => Ok(events),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    /// Blocks until nagd fires, dismisses or removes the nag.
+    pub async fn wait(&mut self, id: u64) -> Result<HistoryEvent, ClientError> {
+        // nagd losing track of the wait is worth knowing, so it goes out on a
+        // fresh connection that is never retried
+        self.connection = None;
+        match self.request(Command::WaitNag { id }).await? {
+            Response::NagEvent { event } => Ok(*event),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    /// Starts the routine over when it is already running.
+    pub async fn start_routine(&mut self, name: &str) -> Result<(), ClientError> {
+        let name = name.to_string();
+        self.request_ok(Command::StartRoutine { name }).await
+    }
+
+    pub async fn routines(&mut self) -> Result<Vec<RoutineStatus>, ClientError> {
+        match self.request(Command::ListRoutines).await? {
+            Response::RoutineList { routines } => Ok(routines),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    pub async fn skip_routine(&mut self, name: &str) -> Result<(), ClientError> {
+        let name = name.to_string();
+        self.request_ok(Command::SkipRoutine { name }).await
+    }
+
+    pub async fn stop_routine(&mut self, name: &str) -> Result<(), ClientError> {
+        let name = name.to_string();
+        self.request_ok(Command::StopRoutine { name }).await
+    }
+
+    /// Turns do not disturb on until `until`, or until it is turned off.
+    pub async fn set_dnd(
+        &mut self,
+        on: bool,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<(), ClientError> {
+        self.request_ok(Command::SetDnd { on, until }).await
+    }
+
+    /// Replaces the environment nagd starts nag_tool and audio_tool in.
+    pub async fn set_session_env(
+        &mut self,
+        env: BTreeMap<String, String>,
+    ) -> Result<(), ClientError> {
+        self.request_ok(Command::SetSessionEnv { env }).await
+    }
+
+    pub async fn quiet(&mut self) -> Result<QuietStatus, ClientError> {
+        match self.request(Command::GetQuiet).await? {
+            Response::Quiet { status } => Ok(status),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    async fn request_ok(&mut self, command: Command) -> Result<(), ClientError> {
+        match self.request(command).await? {
+            Response::Ok => Ok(()),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+
+    async fn request_nags(&mut self, command: Command) -> Result<Vec<Nag>, ClientError> {
+        match self.request(command).await? {
+            Response::NagList { nags } => Ok(nags),
+            response => Err(ClientError::Unexpected(response)),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn exchange(
+    (reader, writer): &mut (BoxedReader, BoxedWriter),
+    command: Command,
+) -> Result<Response, Failure> {
+    send_command(writer, command)
+        .await
+        .map_err(|err| Failure { sent: false, err })?;
+    recv_response(reader)
+        .await
+        .map_err(|err| Failure { sent: true, err })
+}
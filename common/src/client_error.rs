@@ -0,0 +1,108 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::command::Command;
+use crate::error_code::ErrorCode;
+use crate::response::Response;
+
+use std::fmt;
+use std::io;
+
+// ClientError ////////////////////////////////////////////////////////////////
+
+/// What went wrong with a request, shared by the async and blocking clients.
+#[derive(Debug)]
+pub enum ClientError {
+    /// nagd answered with an error
+    Daemon {
+        code: ErrorCode,
+        msg: Option<String>,
+    },
+    /// nagd answered with a response that doesn't fit the command
+    Unexpected(Response),
+    /// nagd didn't connect or answer within the client's timeout
+    Timeout,
+    /// connecting or talking to nagd failed
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+}
+
+// ----------------------------------------------------------------------------
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Daemon { code, msg } => {
+                write!(f, "{} ({:?})", msg.as_deref().unwrap_or("No Message"), code)
+            }
+            ClientError::Unexpected(response) => write!(f, "unexpected response {:?}", response),
+            ClientError::Timeout => write!(f, "timed out talking to nagd"),
+            ClientError::Transport(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl ClientError {
+    /// Whether nagd went away mid request, eg: it was restarted.
+    #[must_use]
+    pub fn is_disconnected(&self) -> bool {
+        matches!(self, ClientError::Transport(err) if is_broken(err.as_ref()))
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+pub(crate) fn into_result(response: Response) -> Result<Response, ClientError> {
+    match response {
+        Response::Error { code, msg } => Err(ClientError::Daemon { code, msg }),
+        response => Ok(response),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// A failed exchange on a kept connection, and whether the command had already
+/// been written out when it failed.
+pub(crate) struct Failure {
+    pub sent: bool,
+    pub err: Box<dyn std::error::Error + Send + Sync>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Failure {
+    /// Whether `command` may go out again on a fresh connection.  Once it was
+    /// written nagd may have run it already, so only commands that change
+    /// nothing are sent twice.
+    pub fn can_resend(&self, command: &Command) -> bool {
+        is_broken(self.err.as_ref()) && (!self.sent || command.is_read_only())
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Whether a kept connection was closed under us, so a fresh one is worth a try.
+pub(crate) fn is_broken(err: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    err.downcast_ref::<io::Error>().is_some_and(|err| {
+        matches!(
+            err.kind(),
+            io::ErrorKind::BrokenPipe
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::UnexpectedEof
+        )
+    })
+}
@@ -3,13 +3,108 @@
 // SPDX-License-Identifier: MIT
 //
 
+use crate::filter::NagFilter;
+use crate::history::HistoryFilter;
 use crate::nag::Nag;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum Command {
-    AddNag { nag: Nag },
-    ListNags,
-    SetNags { nags: Vec<Nag> },
+    AddNag {
+        nag: Nag,
+    },
+    ListNags {
+        #[serde(default)]
+        filter: NagFilter,
+    },
+    SetNags {
+        nags: Vec<Nag>,
+    },
+    RemoveNags {
+        filter: NagFilter,
+    },
+    ClearNags,
+    /// Changes one nag in place, answered with the nag as it is now.
+    EditNag {
+        id: u64,
+        edit: NagEdit,
+    },
+    /// Freezes the countdowns of the matching nags, every nag with an empty
+    /// filter.
+    PauseNags {
+        #[serde(default)]
+        filter: NagFilter,
+    },
+    ResumeNags {
+        #[serde(default)]
+        filter: NagFilter,
+    },
+    ListAlerts,
+    ListHistory {
+        #[serde(default)]
+        filter: HistoryFilter,
+    },
+    /// Answered once the nag fires, is dismissed or is removed.
+    WaitNag {
+        id: u64,
+    },
+    /// Starts the routine from its config, or starts it over.
+    StartRoutine {
+        name: String,
+    },
+    ListRoutines,
+    /// Drops the current step's nag and moves on to the next step now.
+    SkipRoutine {
+        name: String,
+    },
+    StopRoutine {
+        name: String,
+    },
+    /// Turns do not disturb on, until `until` when given, or off.
+    SetDnd {
+        on: bool,
+        #[serde(default)]
+        until: Option<DateTime<Utc>>,
+    },
+    GetQuiet,
+    /// The display and audio variables of the session nag runs in, used for
+    /// the tools nagd starts from then on.
+    SetSessionEnv {
+        env: BTreeMap<String, String>,
+    },
+}
+
+// ----------------------------------------------------------------------------
+
+impl Command {
+    /// Whether the command only looks, so running it twice does no harm.
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Command::ListNags { .. }
+                | Command::ListAlerts
+                | Command::ListHistory { .. }
+                | Command::ListRoutines
+                | Command::GetQuiet
+        )
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// The changes `EditNag` makes, fields that are left out stay as they are.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NagEdit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// pushes the nag back from its end time, or from now when it is overdue,
+    /// a paused nag gets the time added to what it has left
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snooze_seconds: Option<u64>,
 }
@@ -3,8 +3,17 @@
 // SPDX-License-Identifier: MIT
 //
 
+use crate::escalation::Escalation;
+use crate::history::EventKind;
+use crate::nag::Nag;
+use crate::priority::Priority;
+use crate::quiet::QuietConfig;
+use crate::routine::{PomodoroConfig, Routine, POMODORO};
+use crate::sound::{resolve_sound_file, SoundFile};
+
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::Write;
 
 // config object //////////////////////////////////////////////////////////////
@@ -14,6 +23,61 @@ pub struct Config {
     pub edit_tool: Vec<String>,
     pub nag_tool: Vec<String>,
     pub audio_tool: Vec<String>,
+    /// uids besides nagd's own that may connect to the socket
+    #[serde(default)]
+    pub allowed_uids: Vec<u32>,
+    /// days of history nagd keeps, 0 keeps everything
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u64,
+    /// where bare sound file names are looked up, and completed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sounds_dir: Option<String>,
+    /// variables nag hands on to nagd for the tools it starts, nagd may have
+    /// been started before the graphical session
+    #[serde(default = "default_session_env")]
+    pub session_env: Vec<String>,
+    #[serde(default)]
+    pub priorities: Priorities,
+    /// the commands nags may pick as their own nag_tool or audio_tool, by name
+    #[serde(default)]
+    pub tools: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub remote: RemoteConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// used with `nag add --preset <name>` or just `nag <name>`
+    #[serde(default)]
+    pub presets: BTreeMap<String, Preset>,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub quiet: QuietConfig,
+    /// started with `nag routine start <name>`
+    #[serde(default)]
+    pub routines: BTreeMap<String, Routine>,
+    #[serde(default)]
+    pub pomodoro: PomodoroConfig,
+}
+
+// ----------------------------------------------------------------------------
+
+fn default_history_retention_days() -> u64 {
+    30
+}
+
+fn default_session_env() -> Vec<String> {
+    [
+        "DISPLAY",
+        "WAYLAND_DISPLAY",
+        "XAUTHORITY",
+        "I3SOCK",
+        "SWAYSOCK",
+        "XDG_RUNTIME_DIR",
+        "DBUS_SESSION_BUS_ADDRESS",
+        "PULSE_SERVER",
+    ]
+    .map(str::to_string)
+    .to_vec()
 }
 
 // ----------------------------------------------------------------------------
@@ -24,10 +88,387 @@ impl Default for Config {
             edit_tool: vec!["nvim".to_string()],
             nag_tool: vec!["i3-nagbar".to_string(), "-m".to_string()],
             audio_tool: vec!["paplay".to_string()],
+            allowed_uids: Vec::new(),
+            history_retention_days: default_history_retention_days(),
+            sounds_dir: None,
+            session_env: default_session_env(),
+            priorities: Priorities::default(),
+            tools: BTreeMap::new(),
+            remote: RemoteConfig::default(),
+            http: HttpConfig::default(),
+            presets: BTreeMap::new(),
+            hooks: HooksConfig::default(),
+            quiet: QuietConfig::default(),
+            routines: BTreeMap::new(),
+            pomodoro: PomodoroConfig::default(),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl Config {
+    /// Catches what serde can't, so a bad config fails on load rather than on
+    /// first use.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, preset) in &self.presets {
+            if let Some(duration) = &preset.duration {
+                duration_str::parse(duration)
+                    .map_err(|err| format!("bad duration in preset {}: {}", name, err))?;
+            }
+            if let Some(escalation) = &preset.escalation {
+                escalation
+                    .check()
+                    .map_err(|err| format!("preset {}: {}", name, err))?;
+            }
+            for tool in preset.nag_tool.iter().chain(&preset.audio_tool) {
+                if !self.tools.contains_key(tool) {
+                    return Err(format!("preset {} uses unknown tool {}", name, tool));
+                }
+            }
+        }
+
+        let commands = [
+            ("edit_tool", &self.edit_tool),
+            ("nag_tool", &self.nag_tool),
+            ("audio_tool", &self.audio_tool),
+        ];
+        if let Some((name, _)) = commands.iter().find(|(_, command)| command.is_empty()) {
+            return Err(format!("empty command in {}", name));
+        }
+
+        for priority in Priority::ALL {
+            let config = self.priority(priority);
+            if let Some(escalation) = &config.escalation {
+                escalation
+                    .check()
+                    .map_err(|err| format!("priorities.{}: {}", priority.as_str(), err))?;
+            }
+
+            let commands = [
+                ("nag_tool", &config.nag_tool),
+                ("audio_tool", &config.audio_tool),
+                ("escalation_tool", &config.escalation_tool),
+            ];
+            if let Some((name, _)) = commands
+                .iter()
+                .find(|(_, command)| command.as_ref().is_some_and(Vec::is_empty))
+            {
+                return Err(format!(
+                    "empty command in priorities.{}.{}",
+                    priority.as_str(),
+                    name
+                ));
+            }
+        }
+
+        for name in self.routine_names() {
+            let routine = self.routine(name).expect("listed routines exist");
+            if let Some(step) = routine.steps.iter().find(|step| step.too_long()) {
+                return Err(format!(
+                    "routine {} step {} is too many minutes long",
+                    name, step.name
+                ));
+            }
+        }
+
+        if let Some((name, _)) = self.tools.iter().find(|(_, command)| command.is_empty()) {
+            return Err(format!("empty command in tools.{}", name));
+        }
+
+        for kind in EventKind::ALL {
+            if self.hooks.for_event(kind).iter().any(Vec::is_empty) {
+                return Err(format!("empty command in hooks.{}", kind));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn priority(&self, priority: Priority) -> &PriorityConfig {
+        match priority {
+            Priority::Low => &self.priorities.low,
+            Priority::Normal => &self.priorities.normal,
+            Priority::High => &self.priorities.high,
+            Priority::Critical => &self.priorities.critical,
+        }
+    }
+
+    #[must_use]
+    pub fn nag_tool_for(&self, priority: Priority) -> &[String] {
+        self.priority(priority)
+            .nag_tool
+            .as_deref()
+            .unwrap_or(&self.nag_tool)
+    }
+
+    #[must_use]
+    pub fn audio_tool_for(&self, priority: Priority) -> &[String] {
+        self.priority(priority)
+            .audio_tool
+            .as_deref()
+            .unwrap_or(&self.audio_tool)
+    }
+
+    #[must_use]
+    pub fn tool(&self, name: &str) -> Option<&[String]> {
+        self.tools.get(name).map(Vec::as_slice)
+    }
+
+    /// Only commands from `[tools]` may be picked, so clients can't have nagd
+    /// run anything else.
+    pub fn check_tools(&self, nag: &Nag) -> Result<(), String> {
+        match nag
+            .nag_tool
+            .iter()
+            .chain(&nag.audio_tool)
+            .find(|name| !self.tools.contains_key(*name))
+        {
+            Some(name) => Err(format!("{} is not one of the [tools] in the config", name)),
+            None => Ok(()),
+        }
+    }
+
+    /// The routine called `name`, pomodoro is built in unless the config
+    /// defines its own.
+    #[must_use]
+    pub fn routine(&self, name: &str) -> Option<Routine> {
+        match self.routines.get(name) {
+            Some(routine) => Some(routine.clone()),
+            None if name == POMODORO => Some(self.pomodoro.routine()),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    pub fn routine_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.routines.keys().map(String::as_str).collect();
+        if !self.routines.contains_key(POMODORO) {
+            names.push(POMODORO);
+        }
+        names
+    }
+
+    /// The variables out of `vars` that `session_env` allows.
+    pub fn filter_session_env(
+        &self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> BTreeMap<String, String> {
+        vars.into_iter()
+            .filter(|(name, _)| self.session_env.contains(name))
+            .collect()
+    }
+
+    /// Resolves a sound file the way `nag add` does, bare names are looked up
+    /// in `sounds_dir`.
+    pub fn resolve_sound_file(&self, sound_file: &str) -> Result<SoundFile, String> {
+        resolve_sound_file(sound_file, self.sounds_dir.as_deref())
+    }
+}
+
+// per priority overrides /////////////////////////////////////////////////////
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct PriorityConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nag_tool: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_tool: Option<Vec<String>>,
+    /// played when the nag itself has no sound file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_file: Option<String>,
+    /// number of times to play the sound, 0 loops until the nag is dismissed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_repeat: Option<u32>,
+    /// re-alert policy for nags of this priority that don't set their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation: Option<Escalation>,
+    /// an additional channel run with the nag name each time a nag escalates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation_tool: Option<Vec<String>>,
+}
+
+// ----------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Priorities {
+    #[serde(default)]
+    pub low: PriorityConfig,
+    #[serde(default)]
+    pub normal: PriorityConfig,
+    #[serde(default)]
+    pub high: PriorityConfig,
+    #[serde(default)]
+    pub critical: PriorityConfig,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Default for Priorities {
+    fn default() -> Self {
+        let nagbar = |kind: &str| {
+            Some(vec![
+                "i3-nagbar".to_string(),
+                "-t".to_string(),
+                kind.to_string(),
+                "-m".to_string(),
+            ])
+        };
+
+        Self {
+            low: PriorityConfig {
+                nag_tool: nagbar("warning"),
+                ..Default::default()
+            },
+            normal: PriorityConfig::default(),
+            high: PriorityConfig {
+                nag_tool: nagbar("error"),
+                ..Default::default()
+            },
+            critical: PriorityConfig {
+                nag_tool: nagbar("error"),
+                sound_repeat: Some(0),
+                escalation: Some(Escalation {
+                    interval_minutes: 5,
+                    max_count: 3,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+// presets ////////////////////////////////////////////////////////////////////
+
+/// Defaults for `nag add`, anything given on the command line wins.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct Preset {
+    /// name of the nag, the preset's own name when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// how long from now the nag fires, eg "4m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation: Option<Escalation>,
+    /// one of `[tools]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nag_tool: Option<String>,
+    /// one of `[tools]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_tool: Option<String>,
+}
+
+// hooks //////////////////////////////////////////////////////////////////////
+
+/// Commands nagd runs on history events, with the nag as `NAG_*` environment
+/// variables and the event as JSON on stdin.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct HooksConfig {
+    /// seconds a hook gets before it is killed
+    #[serde(default = "default_hook_timeout_seconds")]
+    pub timeout_seconds: u64,
+    /// hooks running at once, the rest wait their turn
+    #[serde(default = "default_hook_max_concurrent")]
+    pub max_concurrent: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub created: Vec<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fired: Vec<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dismissed: Vec<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snoozed: Vec<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edited: Vec<Vec<String>>,
+}
+
+// ----------------------------------------------------------------------------
+
+fn default_hook_timeout_seconds() -> u64 {
+    10
+}
+
+fn default_hook_max_concurrent() -> usize {
+    4
+}
+
+// ----------------------------------------------------------------------------
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: default_hook_timeout_seconds(),
+            max_concurrent: default_hook_max_concurrent(),
+            created: Vec::new(),
+            fired: Vec::new(),
+            dismissed: Vec::new(),
+            snoozed: Vec::new(),
+            removed: Vec::new(),
+            edited: Vec::new(),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl HooksConfig {
+    #[must_use]
+    pub fn for_event(&self, kind: EventKind) -> &[Vec<String>] {
+        match kind {
+            EventKind::Created => &self.created,
+            EventKind::Fired => &self.fired,
+            EventKind::Dismissed => &self.dismissed,
+            EventKind::Snoozed => &self.snoozed,
+            EventKind::Removed => &self.removed,
+            EventKind::Edited => &self.edited,
         }
     }
 }
 
+// remote access //////////////////////////////////////////////////////////////
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct RemoteConfig {
+    /// address nagd accepts remote connections on, eg "0.0.0.0:7878"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen: Option<String>,
+    /// pre-shared token remote clients have to present, nagd won't listen
+    /// without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// certificate and key nagd serves TLS with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<String>,
+    /// certificate nag trusts when connecting with --tls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_ca: Option<String>,
+}
+
+// http api ///////////////////////////////////////////////////////////////////
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct HttpConfig {
+    /// address nagd serves the HTTP API on, eg "127.0.0.1:7879"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen: Option<String>,
+    /// bearer token every request has to carry, nagd won't serve without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
 // static config //////////////////////////////////////////////////////////////
 
 pub static CONFIG: Lazy<Config> = Lazy::new(|| {
@@ -38,7 +479,11 @@ pub static CONFIG: Lazy<Config> = Lazy::new(|| {
     if config_path.exists() {
         let toml_string = std::fs::read_to_string(&config_path).expect("failed to read config");
 
-        toml::from_str(&toml_string).expect("failed to parse toml")
+        let config: Config = toml::from_str(&toml_string).expect("failed to parse toml");
+        if let Err(err) = config.validate() {
+            panic!("invalid config: {}", err);
+        }
+        config
     } else {
         if let Some(parent) = config_path.parent() {
             std::fs::create_dir_all(parent).expect("failed to create config_path");
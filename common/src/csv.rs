@@ -0,0 +1,174 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::dependency::Dependency;
+use crate::escalation::Escalation;
+use crate::nag::Nag;
+use crate::priority::Priority;
+
+use chrono::{DateTime, Local, Utc};
+use duration_str::parse;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+// nag file format ////////////////////////////////////////////////////////////
+
+#[must_use]
+pub fn nag_to_line(nag: &Nag) -> String {
+    // anything beyond the first three columns is written as "key=value" so
+    // that older files (and the defaults) keep the original layout
+    let mut extra = Vec::new();
+    if nag.id != 0 {
+        extra.push(format!("id={}", nag.id));
+    }
+    if nag.priority != Priority::default() {
+        extra.push(format!("priority={}", nag.priority));
+    }
+    if !nag.tags.is_empty() {
+        extra.push(format!("tags={}", nag.tags.join(";")));
+    }
+    if let Some(escalation) = &nag.escalation {
+        extra.push(format!("escalate={}", escalation.to_column()));
+    }
+    if let Some(depends_on) = &nag.depends_on {
+        extra.push(format!("depends={}", depends_on.to_column()));
+    }
+    if let Some(tz) = &nag.tz {
+        extra.push(format!("tz={}", tz));
+    }
+    if let Some(remaining) = nag.remaining_seconds {
+        extra.push(format!("paused={}", remaining));
+    }
+    if let Some(tool) = &nag.nag_tool {
+        extra.push(format!("tool={}", tool));
+    }
+    if let Some(tool) = &nag.audio_tool {
+        extra.push(format!("audio_tool={}", tool));
+    }
+
+    // written in local time for editing, any offset reads back the same
+    let end_time = nag.end_time.with_timezone(&Local).to_rfc3339();
+    let mut columns = vec![end_time, nag.name.clone()];
+    if nag.sound_file.is_some() || !extra.is_empty() {
+        columns.push(nag.sound_file.clone().unwrap_or("None".to_string()));
+    }
+    columns.extend(extra);
+
+    columns
+        .iter()
+        .map(|column| format!("\"{}\"", column))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// ----------------------------------------------------------------------------
+
+fn apply_extra_column(nag: &mut Nag, column: &str) {
+    let Some((key, value)) = column.split_once('=') else {
+        eprintln!("Skipping malformed column {column}");
+        return;
+    };
+
+    match key {
+        "id" => match value.parse() {
+            Ok(id) => nag.id = id,
+            Err(err) => eprintln!("Skipping column {column}: {err}"),
+        },
+        "tags" => {
+            nag.tags = value
+                .split(';')
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect()
+        }
+        "escalate" => match Escalation::from_column(value) {
+            Ok(escalation) => nag.escalation = Some(escalation),
+            Err(err) => eprintln!("Skipping column {column}: {err}"),
+        },
+        "depends" => match Dependency::from_column(value) {
+            Ok(depends_on) => nag.depends_on = Some(depends_on),
+            Err(err) => eprintln!("Skipping column {column}: {err}"),
+        },
+        "tz" => nag.tz = Some(value.to_string()),
+        "tool" => nag.nag_tool = Some(value.to_string()),
+        "audio_tool" => nag.audio_tool = Some(value.to_string()),
+        "paused" => match value.parse() {
+            Ok(remaining) => nag.remaining_seconds = Some(remaining),
+            Err(err) => eprintln!("Skipping column {column}: {err}"),
+        },
+        "priority" => match value.parse() {
+            Ok(priority) => nag.priority = priority,
+            Err(err) => eprintln!("Skipping column {column}: {err}"),
+        },
+        _ => eprintln!("Skipping unknown column {column}"),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn write_nags_to_file<W: Write + Seek>(nags: &Vec<Nag>, writer: &mut W) -> io::Result<()> {
+    writer
+        .seek(SeekFrom::End(0))
+        .expect("Failed to seek to end of file");
+    for nag in nags {
+        let line = nag_to_line(nag);
+        writeln!(writer, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn read_nags_from_file<R: Read + Seek>(
+    read: &mut R,
+) -> Result<Vec<Nag>, Box<dyn std::error::Error>> {
+    read.seek(SeekFrom::Start(0))
+        .expect("failed to seek to beginning of file");
+    let reader = io::BufReader::new(read);
+
+    let mut nags = Vec::new();
+    for line_result in reader.lines() {
+        let line = line_result?;
+        let parts: Vec<&str> = line.split(',').collect();
+
+        if parts.len() < 2 {
+            if !line.is_empty() {
+                eprintln!(
+                    "Skipping malformed line {line}, parts len: {}, parts: {parts:?}",
+                    parts.len()
+                );
+            }
+            continue;
+        }
+
+        let s = parts[0].trim_matches('"');
+        let end_time = match DateTime::parse_from_rfc3339(s) {
+            Ok(datetime) => datetime.with_timezone(&Utc),
+            Err(_) => {
+                println!("NOT a date time, lets try parsing...");
+                Utc::now() + parse(s).expect("Failed to parse string")
+            }
+        };
+        let name = parts[1].trim_matches('"').to_string();
+        let sound_file = match parts.get(2).map(|s| s.trim_matches('"')) {
+            Some(s) if !s.is_empty() && s != "None" => Some(s.to_string()),
+            _ => None,
+        };
+
+        let mut nag = Nag {
+            end_time,
+            name,
+            sound_file,
+            ..Default::default()
+        };
+        for column in parts.iter().skip(3) {
+            apply_extra_column(&mut nag, column.trim_matches('"'));
+        }
+
+        nags.push(nag);
+    }
+
+    Ok(nags)
+}
@@ -0,0 +1,172 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::{format_duration, Nag};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+// Dependency /////////////////////////////////////////////////////////////////
+
+/// The longest offset nagd accepts, about a hundred years.
+pub const MAX_OFFSET_SECONDS: u64 = 100 * 366 * 24 * 60 * 60;
+
+/// Times a nag from another one, nagd resolves it once the anchor happens.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Dependency {
+    /// id of the nag this one is timed from
+    pub parent: u64,
+    #[serde(default)]
+    pub anchor: Anchor,
+    /// how long after the anchor, or before it for `Anchor::Before`
+    pub offset_seconds: u64,
+}
+
+// ----------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    #[default]
+    Fired,
+    Dismissed,
+    Before,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Anchor {
+    pub const ALL: [Anchor; 3] = [Anchor::Fired, Anchor::Dismissed, Anchor::Before];
+
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Anchor::Fired => "fired",
+            Anchor::Dismissed => "dismissed",
+            Anchor::Before => "before",
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl FromStr for Anchor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Anchor::ALL
+            .into_iter()
+            .find(|anchor| anchor.as_str() == s)
+            .ok_or_else(|| format!("unknown anchor {}", s))
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl Dependency {
+    /// Whether the nag waits on its parent, rather than fire at its end_time.
+    #[must_use]
+    pub fn waits(&self) -> bool {
+        self.anchor != Anchor::Before
+    }
+
+    /// Capped at `MAX_OFFSET_SECONDS`, nagd turns longer ones away.
+    #[must_use]
+    pub fn offset(&self) -> Duration {
+        Duration::seconds(self.offset_seconds.min(MAX_OFFSET_SECONDS) as i64)
+    }
+
+    /// When the nag fires for a parent anchored at `anchor_time`, `None` when
+    /// that is past the last date there is.
+    #[must_use]
+    pub fn end_time(&self, anchor_time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.anchor {
+            Anchor::Before => anchor_time.checked_sub_signed(self.offset()),
+            _ => anchor_time.checked_add_signed(self.offset()),
+        }
+    }
+
+    /// Encodes as `parent/anchor/offset_seconds` for the nag file format.
+    #[must_use]
+    pub fn to_column(&self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.parent,
+            self.anchor.as_str(),
+            self.offset_seconds
+        )
+    }
+
+    pub fn from_column(column: &str) -> Result<Self, String> {
+        let mut parts = column.splitn(3, '/');
+
+        let parent = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|err| format!("bad parent in '{}': {}", column, err))?;
+        let anchor = parts
+            .next()
+            .ok_or_else(|| format!("missing anchor in '{}'", column))?
+            .parse()?;
+        let offset_seconds = parts
+            .next()
+            .ok_or_else(|| format!("missing offset in '{}'", column))?
+            .parse()
+            .map_err(|err| format!("bad offset in '{}': {}", column, err))?;
+
+        Ok(Self {
+            parent,
+            anchor,
+            offset_seconds,
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset = format_duration(self.offset());
+        match self.anchor {
+            Anchor::Fired => write!(f, "{} after {} fires", offset, self.parent),
+            Anchor::Dismissed => write!(f, "{} after {} is dismissed", offset, self.parent),
+            Anchor::Before => write!(f, "{} before {} fires", offset, self.parent),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// The id of a nag whose dependencies lead back to itself, if there is one.
+#[must_use]
+pub fn find_cycle(nags: &[Nag]) -> Option<u64> {
+    let parents: HashMap<u64, u64> = nags
+        .iter()
+        .filter_map(|nag| nag.depends_on.as_ref().map(|dep| (nag.id, dep.parent)))
+        .collect();
+
+    let mut ids: Vec<u64> = parents.keys().copied().collect();
+    ids.sort_unstable();
+    for start in ids {
+        let mut seen = HashSet::new();
+        let mut id = start;
+        while let Some(&parent) = parents.get(&id) {
+            if parent == start {
+                return Some(start);
+            }
+            // a loop further up the chain is found from one of its own nags
+            if !seen.insert(parent) {
+                break;
+            }
+            id = parent;
+        }
+    }
+
+    None
+}
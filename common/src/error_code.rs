@@ -11,4 +11,6 @@ pub enum ErrorCode {
     InvalidFormat,
     UnknownCommand,
     NotImplemented,
+    PermissionDenied,
+    NotFound,
 }
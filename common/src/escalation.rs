@@ -0,0 +1,78 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use serde::{Deserialize, Serialize};
+
+// Escalation /////////////////////////////////////////////////////////////////
+
+/// What nagd does when a nag is left on screen without being dismissed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Escalation {
+    /// minutes to wait for the nag to be dismissed before showing it again
+    pub interval_minutes: u64,
+    /// how many times the nag is re-shown before nagd gives up
+    pub max_count: u32,
+    /// loop the sound until dismissed once the nag has escalated
+    #[serde(default)]
+    pub loop_sound: bool,
+    /// a louder sound to switch to once the nag has escalated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_file: Option<String>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Escalation {
+    /// Turns away an interval of 0, which would re-show the nag in a loop.
+    pub fn check(&self) -> Result<(), String> {
+        if self.interval_minutes == 0 {
+            return Err("escalation interval must be at least a minute".to_string());
+        }
+        Ok(())
+    }
+
+    /// Encodes as `interval/max[/loop][/sound_file]` for the nag file format.
+    #[must_use]
+    pub fn to_column(&self) -> String {
+        let mut column = format!("{}/{}", self.interval_minutes, self.max_count);
+        column.push_str(if self.loop_sound { "/loop" } else { "/" });
+        if let Some(sound_file) = &self.sound_file {
+            column.push('/');
+            column.push_str(sound_file);
+        }
+        column
+    }
+
+    pub fn from_column(column: &str) -> Result<Self, String> {
+        let mut parts = column.splitn(4, '/');
+
+        let interval_minutes = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|err| format!("bad escalation interval in '{}': {}", column, err))?;
+        let max_count = parts
+            .next()
+            .ok_or_else(|| format!("missing escalation count in '{}'", column))?
+            .parse()
+            .map_err(|err| format!("bad escalation count in '{}': {}", column, err))?;
+        let loop_sound = parts.next() == Some("loop");
+        let sound_file = parts
+            .next()
+            .filter(|sound_file| !sound_file.is_empty())
+            .map(str::to_string);
+
+        let escalation = Self {
+            interval_minutes,
+            max_count,
+            loop_sound,
+            sound_file,
+        };
+        escalation
+            .check()
+            .map_err(|err| format!("{} in '{}'", err, column))?;
+        Ok(escalation)
+    }
+}
@@ -0,0 +1,92 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::Nag;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// NagFilter //////////////////////////////////////////////////////////////////
+
+/// Selects nags on the daemon side, every set field has to match.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NagFilter {
+    #[serde(default)]
+    pub ids: Vec<u64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub name_regex: Option<String>,
+    #[serde(default)]
+    pub after: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub before: Option<DateTime<Utc>>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl NagFilter {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == NagFilter::default()
+    }
+
+    pub fn matcher(&self) -> Result<NagMatcher<'_>, regex::Error> {
+        let regex = self.name_regex.as_deref().map(Regex::new).transpose()?;
+        Ok(NagMatcher {
+            filter: self,
+            regex,
+        })
+    }
+}
+
+// NagMatcher /////////////////////////////////////////////////////////////////
+
+pub struct NagMatcher<'a> {
+    filter: &'a NagFilter,
+    regex: Option<Regex>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl NagMatcher<'_> {
+    #[must_use]
+    pub fn matches(&self, nag: &Nag) -> bool {
+        let filter = self.filter;
+
+        if !filter.ids.is_empty() && !filter.ids.contains(&nag.id) {
+            return false;
+        }
+
+        if !filter.tags.iter().all(|tag| nag.tags.contains(tag)) {
+            return false;
+        }
+
+        if let Some(name) = &filter.name {
+            if !nag.name.to_lowercase().contains(&name.to_lowercase()) {
+                return false;
+            }
+        }
+
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(&nag.name) {
+                return false;
+            }
+        }
+
+        if filter.after.is_some_and(|after| nag.end_time < after) {
+            return false;
+        }
+
+        if filter.before.is_some_and(|before| nag.end_time > before) {
+            return false;
+        }
+
+        true
+    }
+}
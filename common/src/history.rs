@@ -0,0 +1,156 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::Nag;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+#[cfg(feature = "server")]
+use std::path::PathBuf;
+
+// history events /////////////////////////////////////////////////////////////
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Created,
+    Fired,
+    Dismissed,
+    Snoozed,
+    Removed,
+    Edited,
+}
+
+// ----------------------------------------------------------------------------
+
+impl EventKind {
+    pub const ALL: [EventKind; 6] = [
+        EventKind::Created,
+        EventKind::Fired,
+        EventKind::Dismissed,
+        EventKind::Snoozed,
+        EventKind::Removed,
+        EventKind::Edited,
+    ];
+}
+
+// ----------------------------------------------------------------------------
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            EventKind::Created => "created",
+            EventKind::Fired => "fired",
+            EventKind::Dismissed => "dismissed",
+            EventKind::Snoozed => "snoozed",
+            EventKind::Removed => "removed",
+            EventKind::Edited => "edited",
+        };
+        f.pad(kind)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEvent {
+    pub time: DateTime<Utc>,
+    pub kind: EventKind,
+    pub nag: Nag,
+}
+
+// history filter /////////////////////////////////////////////////////////////
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HistoryFilter {
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// case insensitive substring of the nag name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// only the latest this many matching events
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+// ----------------------------------------------------------------------------
+
+/// How many of the latest events nagd sends when the filter has neither a
+/// `since` nor a `limit`, so a long history still fits in a single message.
+pub const HISTORY_LIMIT: usize = 1000;
+
+// ----------------------------------------------------------------------------
+
+impl HistoryFilter {
+    /// The `limit` nagd applies, `HISTORY_LIMIT` unless the filter asks for
+    /// something else.
+    #[must_use]
+    pub fn limit(&self) -> Option<usize> {
+        match (self.limit, self.since) {
+            (Some(limit), _) => Some(limit),
+            (None, None) => Some(HISTORY_LIMIT),
+            (None, Some(_)) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn matches(&self, event: &HistoryEvent) -> bool {
+        if self.since.is_some_and(|since| event.time < since) {
+            return false;
+        }
+
+        if self.until.is_some_and(|until| event.time > until) {
+            return false;
+        }
+
+        if let Some(name) = &self.name {
+            if !event.nag.name.to_lowercase().contains(&name.to_lowercase()) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+// history store //////////////////////////////////////////////////////////////
+
+#[cfg(feature = "server")]
+#[must_use]
+pub fn history_path() -> PathBuf {
+    dirs::data_dir()
+        .expect("Could not get data_dir")
+        .join("nag/history.jsonl")
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn write_event<W: Write>(event: &HistoryEvent, writer: &mut W) -> io::Result<()> {
+    let line = serde_json::to_string(event)?;
+    writeln!(writer, "{}", line)
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn read_events<R: BufRead>(reader: R) -> io::Result<Vec<HistoryEvent>> {
+    let mut events = Vec::new();
+    for line_result in reader.lines() {
+        let line = line_result?;
+        if line.is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str(&line) {
+            Ok(event) => events.push(event),
+            Err(err) => eprintln!("Skipping malformed history line {line} ({err})"),
+        }
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,729 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::Nag;
+use crate::priority::Priority;
+use crate::timezone::resolve_local;
+
+use chrono::{
+    DateTime, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+
+// iCalendar (RFC 5545) import and export /////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IcsComponent {
+    Event,
+    Todo,
+}
+
+// ----------------------------------------------------------------------------
+
+// the most occurrences a single RRULE may expand to from the import window
+// on, guards against rules like FREQ=MINUTELY without an end
+const MAX_OCCURRENCES: u32 = 100_000;
+
+// parsed calendar tree ///////////////////////////////////////////////////////
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[derive(Default)]
+struct Component {
+    name: String,
+    properties: Vec<Property>,
+    components: Vec<Component>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Component {
+    fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Component> {
+        self.components
+            .iter()
+            .filter(move |component| component.name == name)
+    }
+}
+
+// export /////////////////////////////////////////////////////////////////////
+
+#[must_use]
+pub fn nags_to_ics(nags: &[Nag], component: IcsComponent) -> String {
+    let (kind, time_property, related) = match component {
+        IcsComponent::Event => ("VEVENT", "DTSTART", "START"),
+        IcsComponent::Todo => ("VTODO", "DUE", "END"),
+    };
+    let stamp = format_utc(&Utc::now());
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//nag-rs//nag//EN".to_string(),
+    ];
+    for nag in nags {
+        lines.push(format!("BEGIN:{}", kind));
+        lines.push(format!(
+            "UID:nag-{}-{}@nag-rs",
+            nag.id,
+            nag.end_time.timestamp()
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("{}:{}", time_property, format_utc(&nag.end_time)));
+        lines.push(format!("SUMMARY:{}", escape_text(&nag.name)));
+        if let Some(priority) = priority_to_ics(nag.priority) {
+            lines.push(format!("PRIORITY:{}", priority));
+        }
+        if !nag.tags.is_empty() {
+            let tags: Vec<String> = nag.tags.iter().map(|tag| escape_text(tag)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+
+        lines.push("BEGIN:VALARM".to_string());
+        if let Some(sound_file) = &nag.sound_file {
+            lines.push("ACTION:AUDIO".to_string());
+            lines.push(format!("ATTACH:{}", sound_file));
+        } else {
+            lines.push("ACTION:DISPLAY".to_string());
+            lines.push(format!("DESCRIPTION:{}", escape_text(&nag.name)));
+        }
+        lines.push(format!("TRIGGER;RELATED={}:PT0S", related));
+        lines.push("END:VALARM".to_string());
+
+        lines.push(format!("END:{}", kind));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold_line(&line));
+        ics.push_str("\r\n");
+    }
+    ics
+}
+
+// ----------------------------------------------------------------------------
+
+fn format_utc(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// ----------------------------------------------------------------------------
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// ----------------------------------------------------------------------------
+
+fn fold_line(line: &str) -> String {
+    // lines are limited to 75 octets, continuations start with a space
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+// ----------------------------------------------------------------------------
+
+fn priority_to_ics(priority: Priority) -> Option<u8> {
+    match priority {
+        Priority::Critical => Some(1),
+        Priority::High => Some(3),
+        Priority::Normal => None,
+        Priority::Low => Some(9),
+    }
+}
+
+// import /////////////////////////////////////////////////////////////////////
+
+/// Turns every alarm (or event/todo without alarms) firing between `from` and
+/// `until` into a nag, expanding simple RRULE recurrences.
+pub fn nags_from_ics(
+    ics: &str,
+    from: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<Nag>, String> {
+    let root = parse_components(ics)?;
+
+    let mut nags = Vec::new();
+    for calendar in root.children("VCALENDAR") {
+        for component in &calendar.components {
+            let anchor = match component.name.as_str() {
+                "VEVENT" => component.property("DTSTART"),
+                "VTODO" => component
+                    .property("DUE")
+                    .or_else(|| component.property("DTSTART")),
+                _ => continue,
+            };
+            let Some(anchor) = anchor else {
+                eprintln!("Skipping {} without a start or due time", component.name);
+                continue;
+            };
+
+            let start = IcsTime::parse(anchor)?;
+            let span = match component.property("DTEND") {
+                Some(dtend) => IcsTime::parse(dtend)?.to_utc() - start.to_utc(),
+                None => Duration::zero(),
+            };
+
+            // alarms can fire before their occurrence, so look that far past
+            // the end of the window for occurrences, and after it (or after
+            // its end), so look that far back before the start of the window
+            let mut lead = Duration::zero();
+            let mut lag = Duration::zero();
+            for alarm in component.children("VALARM") {
+                if let Some(trigger) = alarm.property("TRIGGER") {
+                    if trigger.param("VALUE") != Some("DATE-TIME") {
+                        let offset = parse_duration(&trigger.value)?;
+                        let offset = match trigger.param("RELATED") {
+                            Some("END") => offset.checked_add(&span).ok_or_else(|| {
+                                format!("{} has an alarm too far after it", component.name)
+                            })?,
+                            _ => offset,
+                        };
+                        lead = lead.max(-offset);
+                        lag = lag.max(offset);
+                    }
+                }
+            }
+
+            let last = until
+                .checked_add_signed(lead)
+                .ok_or_else(|| format!("{} has an alarm too far ahead", component.name))?;
+            let earliest = from
+                .checked_sub_signed(lag)
+                .unwrap_or(DateTime::<Utc>::MIN_UTC);
+            for occurrence in occurrences(component, &start, earliest, last)? {
+                nags.extend(
+                    nags_for_occurrence(component, occurrence, &start)?
+                        .into_iter()
+                        .filter(|nag| nag.end_time >= from && nag.end_time <= until),
+                );
+            }
+        }
+    }
+
+    nags.sort_by_key(|nag| nag.end_time);
+    Ok(nags)
+}
+
+// ----------------------------------------------------------------------------
+
+fn nags_for_occurrence(
+    component: &Component,
+    occurrence: DateTime<Utc>,
+    start: &IcsTime,
+) -> Result<Vec<Nag>, String> {
+    let template = Nag {
+        end_time: occurrence,
+        name: component
+            .property("SUMMARY")
+            .map(|summary| unescape_text(&summary.value))
+            .unwrap_or("Untitled".to_string()),
+        priority: component
+            .property("PRIORITY")
+            .and_then(|priority| priority.value.trim().parse().ok())
+            .map(priority_from_ics)
+            .unwrap_or_default(),
+        tags: component
+            .properties
+            .iter()
+            .filter(|property| property.name == "CATEGORIES")
+            .flat_map(|property| split_list(&property.value))
+            .collect(),
+        tz: match &start.zone {
+            Zone::Named(tz) => Some(tz.name().to_string()),
+            _ => None,
+        },
+        ..Default::default()
+    };
+
+    let alarms: Vec<&Component> = component.children("VALARM").collect();
+    if alarms.is_empty() {
+        return Ok(vec![template]);
+    }
+
+    // RELATED=END is measured from DTEND, shifted along with the occurrence
+    let out_of_range = || format!("{} is past the last date there is", component.name);
+    let end = match component.property("DTEND") {
+        Some(dtend) => Some(
+            occurrence
+                .checked_add_signed(IcsTime::parse(dtend)?.to_utc() - start.to_utc())
+                .ok_or_else(out_of_range)?,
+        ),
+        None => None,
+    };
+
+    let mut nags = Vec::new();
+    for alarm in alarms {
+        let Some(trigger) = alarm.property("TRIGGER") else {
+            continue;
+        };
+
+        let end_time = if trigger.param("VALUE") == Some("DATE-TIME") {
+            IcsTime::parse(trigger)?.to_utc()
+        } else {
+            let offset = parse_duration(&trigger.value)?;
+            let related = match trigger.param("RELATED") {
+                Some("END") => end.unwrap_or(occurrence),
+                _ => occurrence,
+            };
+            related
+                .checked_add_signed(offset)
+                .ok_or_else(out_of_range)?
+        };
+
+        let sound_file = match alarm.property("ACTION").map(|a| a.value.as_str()) {
+            Some("AUDIO") => alarm.property("ATTACH").map(|attach| {
+                let value = attach.value.trim();
+                value.strip_prefix("file://").unwrap_or(value).to_string()
+            }),
+            _ => None,
+        };
+
+        nags.push(Nag {
+            end_time,
+            sound_file,
+            ..template.clone()
+        });
+    }
+
+    Ok(nags)
+}
+
+// ----------------------------------------------------------------------------
+
+fn priority_from_ics(priority: u8) -> Priority {
+    match priority {
+        1..=2 => Priority::Critical,
+        3..=4 => Priority::High,
+        6..=9 => Priority::Low,
+        _ => Priority::Normal,
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// ----------------------------------------------------------------------------
+
+fn split_list(value: &str) -> Vec<String> {
+    // split on commas that aren't escaped
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        if c == ',' && !escaped {
+            items.push(unescape_text(&item));
+            item.clear();
+            continue;
+        }
+        escaped = c == '\\' && !escaped;
+        item.push(c);
+    }
+    items.push(unescape_text(&item));
+
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+// parsing ////////////////////////////////////////////////////////////////////
+
+fn parse_components(ics: &str) -> Result<Component, String> {
+    // unfold continuation lines first
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut stack = vec![Component::default()];
+    for line in lines {
+        let property = parse_property(&line)?;
+        match property.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                name: property.value.to_ascii_uppercase(),
+                ..Default::default()
+            }),
+            "END" => {
+                let component = stack.pop().filter(|_| !stack.is_empty());
+                match (component, stack.last_mut()) {
+                    (Some(component), Some(parent))
+                        if component.name.eq_ignore_ascii_case(&property.value) =>
+                    {
+                        parent.components.push(component)
+                    }
+                    _ => return Err(format!("unexpected END:{}", property.value)),
+                }
+            }
+            _ => stack
+                .last_mut()
+                .expect("the root component is never popped")
+                .properties
+                .push(property),
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err("unterminated component".to_string());
+    }
+    Ok(stack.remove(0))
+}
+
+// ----------------------------------------------------------------------------
+
+fn parse_property(line: &str) -> Result<Property, String> {
+    // the value starts at the first colon that isn't inside a quoted parameter
+    let mut in_quotes = false;
+    let split = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        c == ':' && !in_quotes
+    });
+    let Some((split, _)) = split else {
+        return Err(format!("malformed line '{}'", line));
+    };
+
+    let mut parts = line[..split].split(';');
+    let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+
+    Ok(Property {
+        name,
+        params,
+        value: line[split + 1..].to_string(),
+    })
+}
+
+// ----------------------------------------------------------------------------
+
+/// Parses an RFC 5545 duration such as `-PT15M` or `P1DT2H`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}'", value);
+
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            _ => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                let part = match (c, in_time) {
+                    ('W', false) => Duration::try_weeks(n),
+                    ('D', false) => Duration::try_days(n),
+                    ('H', true) => Duration::try_hours(n),
+                    ('M', true) => Duration::try_minutes(n),
+                    ('S', true) => Duration::try_seconds(n),
+                    _ => return Err(invalid()),
+                };
+                // the text is untrusted, a duration past what chrono holds is
+                // an error rather than a panic
+                duration = part
+                    .and_then(|part| duration.checked_add(&part))
+                    .ok_or_else(|| format!("duration '{}' is too long", value))?;
+            }
+        }
+    }
+
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(if negative { -duration } else { duration })
+}
+
+// times //////////////////////////////////////////////////////////////////////
+
+enum Zone {
+    Utc,
+    Named(Tz),
+    Floating,
+}
+
+// ----------------------------------------------------------------------------
+
+struct IcsTime {
+    local: NaiveDateTime,
+    zone: Zone,
+}
+
+// ----------------------------------------------------------------------------
+
+impl IcsTime {
+    fn parse(property: &Property) -> Result<Self, String> {
+        let value = property.value.trim();
+
+        let (value, utc) = match value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+
+        let local = if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+            date.and_time(NaiveTime::MIN)
+        } else {
+            NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+                .map_err(|err| format!("invalid time '{}' ({})", property.value, err))?
+        };
+
+        let zone = if utc {
+            Zone::Utc
+        } else if let Some(tzid) = property.param("TZID") {
+            match tzid.parse::<Tz>() {
+                Ok(tz) => Zone::Named(tz),
+                Err(_) => {
+                    eprintln!("Unknown TZID {tzid}, using the local timezone");
+                    Zone::Floating
+                }
+            }
+        } else {
+            Zone::Floating
+        };
+
+        Ok(Self { local, zone })
+    }
+
+    // ------------------------------------------------------------------------
+
+    fn to_utc(&self) -> DateTime<Utc> {
+        self.resolve(self.local)
+    }
+
+    // ------------------------------------------------------------------------
+
+    /// Places a wall clock time in this time's zone.
+    fn resolve(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match &self.zone {
+            Zone::Utc => Utc.from_utc_datetime(&local),
+            Zone::Named(tz) => resolve_local(tz, local),
+            Zone::Floating => resolve_local(&Local, local),
+        }
+    }
+}
+
+// recurrence /////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+enum Frequency {
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// ----------------------------------------------------------------------------
+
+/// The step of a fixed length rule a day before `earliest`, the day covers
+/// wall clock steps drifting from UTC over DST changes.  Months and years
+/// vary in length and never get near `MAX_OCCURRENCES` anyway.
+fn first_step(
+    frequency: Frequency,
+    interval: u32,
+    first: DateTime<Utc>,
+    earliest: DateTime<Utc>,
+) -> u32 {
+    let step_seconds = match frequency {
+        Frequency::Minutely => 60,
+        Frequency::Hourly => 60 * 60,
+        Frequency::Daily => 24 * 60 * 60,
+        Frequency::Weekly => 7 * 24 * 60 * 60,
+        Frequency::Monthly | Frequency::Yearly => return 0,
+    };
+
+    let behind = (earliest - first - Duration::days(1)).num_seconds();
+    if behind <= 0 {
+        return 0;
+    }
+    u32::try_from(behind / step_seconds / i64::from(interval)).unwrap_or(u32::MAX)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Occurrences up to `until`, rules that started long ago pick up near
+/// `earliest` rather than stepping all the way from their first one.
+fn occurrences(
+    component: &Component,
+    start: &IcsTime,
+    earliest: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, String> {
+    let first = start.to_utc();
+    let Some(rrule) = component.property("RRULE") else {
+        return Ok(vec![first]);
+    };
+
+    let mut frequency = None;
+    let mut interval = 1;
+    let mut count = None;
+    let mut rule_until = None;
+    for part in rrule.value.split(';') {
+        let Some((key, value)) = part.split_once('=') else {
+            continue;
+        };
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = match value.to_ascii_uppercase().as_str() {
+                    "MINUTELY" => Some(Frequency::Minutely),
+                    "HOURLY" => Some(Frequency::Hourly),
+                    "DAILY" => Some(Frequency::Daily),
+                    "WEEKLY" => Some(Frequency::Weekly),
+                    "MONTHLY" => Some(Frequency::Monthly),
+                    "YEARLY" => Some(Frequency::Yearly),
+                    _ => None,
+                }
+            }
+            "INTERVAL" => {
+                interval = value
+                    .parse()
+                    .ok()
+                    .filter(|interval| *interval > 0)
+                    .ok_or_else(|| format!("bad RRULE {part}"))?
+            }
+            "COUNT" => count = Some(value.parse().map_err(|_| format!("bad RRULE {part}"))?),
+            "UNTIL" => {
+                let until_property = Property {
+                    name: "UNTIL".to_string(),
+                    params: rrule.params.clone(),
+                    value: value.to_string(),
+                };
+                rule_until = Some(IcsTime::parse(&until_property)?.to_utc());
+            }
+            "WKST" => {}
+            _ => {
+                eprintln!("Unsupported RRULE part {part}, only using the first occurrence");
+                return Ok(vec![first]);
+            }
+        }
+    }
+    let Some(frequency) = frequency else {
+        eprintln!(
+            "Unsupported RRULE {}, only using the first occurrence",
+            rrule.value
+        );
+        return Ok(vec![first]);
+    };
+
+    // no timezone is a day off UTC, so later wall clock times are past until
+    let horizon = until
+        .naive_utc()
+        .checked_add_signed(Duration::days(1))
+        .unwrap_or(NaiveDateTime::MAX);
+    let add = |duration: Option<Duration>| duration.and_then(|d| start.local.checked_add_signed(d));
+
+    // step in wall clock time so recurrences keep their local time over DST
+    let mut occurrences = Vec::new();
+    let skipped = first_step(frequency, interval, first, earliest);
+    let limit = count
+        .unwrap_or(u32::MAX)
+        .min(skipped.saturating_add(MAX_OCCURRENCES));
+    let mut ended = false;
+    for n in skipped..limit {
+        let Some(step) = n.checked_mul(interval) else {
+            ended = true;
+            break;
+        };
+        let local = match frequency {
+            Frequency::Minutely => add(Duration::try_minutes(step.into())),
+            Frequency::Hourly => add(Duration::try_hours(step.into())),
+            Frequency::Daily => add(Duration::try_days(step.into())),
+            Frequency::Weekly => add(Duration::try_weeks(step.into())),
+            Frequency::Monthly => start.local.checked_add_months(Months::new(step)),
+            Frequency::Yearly => step
+                .checked_mul(12)
+                .and_then(|months| start.local.checked_add_months(Months::new(months))),
+        };
+        let Some(local) = local.filter(|local| *local <= horizon) else {
+            ended = true;
+            break;
+        };
+
+        let occurrence = start.resolve(local);
+        if rule_until.is_some_and(|rule_until| occurrence > rule_until) || occurrence > until {
+            ended = true;
+            break;
+        }
+        occurrences.push(occurrence);
+    }
+
+    if !ended && count.is_none_or(|count| count > limit) {
+        eprintln!(
+            "RRULE {} has more than {} occurrences, only using the first ones",
+            rrule.value, MAX_OCCURRENCES
+        );
+    }
+
+    Ok(occurrences)
+}
@@ -3,20 +3,110 @@
 // SPDX-License-Identifier: MIT
 //
 
+// protocol types, `proto`
+#[cfg(feature = "proto")]
+pub mod alert;
+#[cfg(feature = "proto")]
 pub mod command;
-pub mod config;
+#[cfg(feature = "proto")]
+pub mod dependency;
+#[cfg(feature = "proto")]
 pub mod error_code;
+#[cfg(feature = "proto")]
+pub mod escalation;
+#[cfg(feature = "proto")]
+pub mod filter;
+#[cfg(feature = "proto")]
+pub mod history;
+#[cfg(feature = "proto")]
 pub mod nag;
-pub mod recv;
+#[cfg(feature = "proto")]
+pub mod priority;
+#[cfg(feature = "proto")]
+pub mod quiet;
+#[cfg(feature = "proto")]
 pub mod response;
+#[cfg(feature = "proto")]
+pub mod routine;
+
+// talking to nagd, `async`, `blocking`, `client` and `server`
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(any(feature = "client", feature = "blocking"))]
+pub mod client_error;
+#[cfg(feature = "async")]
+pub mod recv;
+#[cfg(any(feature = "client", feature = "server"))]
+pub mod remote;
+#[cfg(feature = "async")]
 pub mod send;
+#[cfg(all(feature = "tls", any(feature = "client", feature = "server")))]
+pub mod tls;
 
-pub use command::Command;
-pub use config::{Config, CONFIG};
+// files and times, `config`, `csv`, `ics` and `tz`
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "ics")]
+pub mod ics;
+#[cfg(feature = "config")]
+pub mod sound;
+#[cfg(feature = "tz")]
+pub mod timezone;
+
+#[cfg(feature = "proto")]
+pub use alert::Alert;
+#[cfg(feature = "client")]
+pub use client::{Client, Endpoint};
+#[cfg(any(feature = "client", feature = "blocking"))]
+pub use client_error::ClientError;
+#[cfg(feature = "proto")]
+pub use command::{Command, NagEdit};
+#[cfg(feature = "config")]
+pub use config::{Config, HooksConfig, Preset, CONFIG};
+#[cfg(feature = "csv")]
+pub use csv::{nag_to_line, read_nags_from_file, write_nags_to_file};
+#[cfg(feature = "proto")]
+pub use dependency::{find_cycle, Anchor, Dependency, MAX_OFFSET_SECONDS};
+#[cfg(feature = "proto")]
 pub use error_code::ErrorCode;
-pub use nag::{nag_to_line, read_nags_from_file, time_remaining, write_nags_to_file, Nag};
-pub use recv::{recv_command, recv_message, recv_response};
+#[cfg(feature = "proto")]
+pub use escalation::Escalation;
+#[cfg(feature = "proto")]
+pub use filter::{NagFilter, NagMatcher};
+#[cfg(feature = "proto")]
+pub use history::{EventKind, HistoryEvent, HistoryFilter, HISTORY_LIMIT};
+#[cfg(feature = "ics")]
+pub use ics::{nags_from_ics, nags_to_ics, IcsComponent};
+#[cfg(feature = "proto")]
+pub use nag::{format_duration, time_remaining, Nag, MAX_REMAINING_SECONDS};
+#[cfg(feature = "proto")]
+pub use priority::Priority;
+#[cfg(feature = "proto")]
+pub use quiet::{QuietConfig, QuietEffect, QuietHours, QuietMode, QuietStatus};
+#[cfg(feature = "async")]
+pub use recv::{recv_command, recv_message, recv_message_within, recv_response};
+#[cfg(feature = "server")]
+pub use remote::{accept_handshake, tokens_match};
+#[cfg(feature = "client")]
+pub use remote::{connect_tcp, send_handshake};
+#[cfg(feature = "proto")]
 pub use response::Response;
+#[cfg(feature = "proto")]
+pub use routine::{Advance, PomodoroConfig, Routine, RoutineStatus, RoutineStep, POMODORO};
+#[cfg(feature = "async")]
 pub use send::{send_command, send_message, send_response};
+#[cfg(feature = "config")]
+pub use sound::{audio_format, expand_path, resolve_sound_file, SoundFile};
+#[cfg(feature = "tz")]
+pub use timezone::{format_wall_time, nag_tz, next_wall_time, parse_tz, resolve_local};
+
+/// In a directory of its own, nagd sets that directory's permissions.
+pub const COMSOCK_PATH: &str = "/home/hsmith/.tmp/nag/nag.sock";
 
-pub const COMSOCK_PATH: &str = "/home/hsmith/.tmp/nag.sock";
+/// The longest message either side reads, checked before anything is allocated
+/// since the length comes from the peer.
+pub const MAX_MESSAGE_LEN: u32 = 4 * 1024 * 1024;
@@ -3,18 +3,112 @@
 // SPDX-License-Identifier: MIT
 //
 
+use crate::dependency::Dependency;
+use crate::escalation::Escalation;
+use crate::priority::Priority;
+
 use chrono::{DateTime, Duration, Utc};
-use duration_str::parse;
 use serde::{Deserialize, Serialize};
-use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
 
 // Nag data structure /////////////////////////////////////////////////////////
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Nag {
+    /// assigned by nagd, 0 until the nag has been added
+    #[serde(default)]
+    pub id: u64,
     pub end_time: DateTime<Utc>,
     pub name: String,
     pub sound_file: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// overrides the escalation configured for the nag's priority
+    #[serde(default)]
+    pub escalation: Option<Escalation>,
+    /// times the nag from another nag instead, until nagd resolves it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Dependency>,
+    /// IANA timezone the nag was set in, it is shown in that timezone too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tz: Option<String>,
+    /// the time that was left when the nag was paused, end_time is stale
+    /// until it is resumed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_seconds: Option<u64>,
+    /// names a command from `[tools]` in nagd's config to show the nag with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nag_tool: Option<String>,
+    /// names a command from `[tools]` in nagd's config to play the sound with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_tool: Option<String>,
+}
+
+// ----------------------------------------------------------------------------
+
+/// The most time a paused nag may have left, about a hundred years.
+pub const MAX_REMAINING_SECONDS: u64 = 100 * 366 * 24 * 60 * 60;
+
+// ----------------------------------------------------------------------------
+
+impl Nag {
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.remaining_seconds.is_some()
+    }
+
+    /// Freezes the countdown, until [`Nag::resume`].
+    pub fn pause(&mut self, now: DateTime<Utc>) {
+        let remaining = (self.end_time - now).num_seconds().max(0);
+        self.remaining_seconds = Some(remaining as u64);
+    }
+
+    /// Picks the countdown up again from `now`.
+    pub fn resume(&mut self, now: DateTime<Utc>) {
+        if let Some(remaining) = self.remaining_seconds.take() {
+            self.end_time = now + remaining_duration(remaining);
+        }
+    }
+
+    /// Pushes the nag back by `seconds` from its end_time, or from `now` when
+    /// it is overdue, a paused nag gets them added to what it has left.
+    pub fn snooze(&mut self, seconds: u64, now: DateTime<Utc>) -> Result<(), String> {
+        let too_long = || format!("can't snooze {} that long", self.name);
+        match self.remaining_seconds {
+            Some(remaining) => {
+                let remaining = remaining
+                    .checked_add(seconds)
+                    .filter(|remaining| *remaining <= MAX_REMAINING_SECONDS)
+                    .ok_or_else(too_long)?;
+                self.remaining_seconds = Some(remaining);
+            }
+            None => {
+                self.end_time = i64::try_from(seconds)
+                    .ok()
+                    .and_then(Duration::try_seconds)
+                    .and_then(|snooze| self.end_time.max(now).checked_add_signed(snooze))
+                    .ok_or_else(too_long)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`time_remaining`], but frozen while the nag is paused.
+    #[must_use]
+    pub fn remaining(&self) -> String {
+        match self.remaining_seconds {
+            Some(remaining) => format_duration(remaining_duration(remaining)),
+            None => time_remaining(&self.end_time),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+// nagd turns away paused nags with more left than MAX_REMAINING_SECONDS
+fn remaining_duration(remaining: u64) -> Duration {
+    Duration::seconds(remaining.min(MAX_REMAINING_SECONDS) as i64)
 }
 
 // ----------------------------------------------------------------------------
@@ -28,109 +122,41 @@ pub fn time_remaining(end_time: &DateTime<Utc>) -> String {
         return "0".to_string();
     }
 
-    let mut remaining = String::new();
+    format_duration(duration)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Formats as eg: `1d2h3m4s`, leaving out the units that are zero.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let mut formatted = String::new();
     let seconds = duration.num_seconds();
 
     let days = seconds / (24 * 60 * 60);
     if days > 0 {
-        remaining.push_str(&format!("{}d", days));
+        formatted.push_str(&format!("{}d", days));
     }
 
     let hours_left = seconds - (days * 24 * 60 * 60);
     let hours = hours_left / (60 * 60);
     if hours > 0 {
-        remaining.push_str(&format!("{}h", hours));
+        formatted.push_str(&format!("{}h", hours));
     }
 
     let minutes_left = hours_left - (hours * 60 * 60);
     let minutes = minutes_left / 60;
     if minutes > 0 {
-        remaining.push_str(&format!("{}m", minutes));
+        formatted.push_str(&format!("{}m", minutes));
     }
 
     let seconds_left = minutes_left - (minutes * 60);
     if seconds_left > 0 {
-        remaining.push_str(&format!("{}s", seconds_left));
-    }
-
-    remaining
-}
-
-// ----------------------------------------------------------------------------
-
-#[must_use]
-pub fn nag_to_line(nag: &Nag) -> String {
-    if let Some(sound_file) = &nag.sound_file {
-        format!(
-            "\"{}\",\"{}\",\"{}\"",
-            nag.end_time.to_rfc3339(),
-            nag.name,
-            sound_file
-        )
-    } else {
-        format!("\"{}\",\"{}\"", nag.end_time.to_rfc3339(), nag.name)
-    }
-}
-
-// ----------------------------------------------------------------------------
-
-pub fn write_nags_to_file<W: Write + Seek>(nags: &Vec<Nag>, writer: &mut W) -> io::Result<()> {
-    writer
-        .seek(SeekFrom::End(0))
-        .expect("Failed to seek to end of file");
-    for nag in nags {
-        let line = nag_to_line(nag);
-        writeln!(writer, "{}", line)?;
+        formatted.push_str(&format!("{}s", seconds_left));
     }
 
-    Ok(())
-}
-
-// ----------------------------------------------------------------------------
-
-pub fn read_nags_from_file<R: Read + Seek>(
-    read: &mut R,
-) -> Result<Vec<Nag>, Box<dyn std::error::Error>> {
-    read.seek(SeekFrom::Start(0))
-        .expect("failed to seek to beginning of file");
-    let reader = io::BufReader::new(read);
-
-    let mut nags = Vec::new();
-    for line_result in reader.lines() {
-        let line = line_result?;
-        let parts: Vec<&str> = line.split(',').collect();
-
-        if parts.len() < 2 {
-            if !line.is_empty() {
-                eprintln!(
-                    "Skipping malformed line {line}, parts len: {}, parts: {parts:?}",
-                    parts.len()
-                );
-            }
-            continue;
-        }
-
-        let s = parts[0].trim_matches('"');
-        let end_time = match DateTime::parse_from_rfc3339(s) {
-            Ok(datetime) => datetime.with_timezone(&Utc),
-            Err(_) => {
-                println!("NOT a date time, lets try parsing...");
-                Utc::now() + parse(s).expect("Failed to parse string")
-            }
-        };
-        let name = parts[1].trim_matches('"').to_string();
-        let sound_file = if parts.len() > 2 && parts[2] != "None" {
-            Some(parts[2].trim_matches('"').to_string())
-        } else {
-            None
-        };
-
-        nags.push(Nag {
-            end_time,
-            name,
-            sound_file,
-        });
+    if formatted.is_empty() {
+        formatted.push_str("0s");
     }
-
-    Ok(nags)
+    formatted
 }
@@ -0,0 +1,168 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::Nag;
+use crate::priority::Priority;
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// quiet hours ////////////////////////////////////////////////////////////////
+
+/// What nagd does with nags that come due while it is quiet.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietMode {
+    /// hold nags back until it is no longer quiet
+    #[default]
+    Defer,
+    /// show nags without playing their sound
+    Silent,
+    /// let critical nags through and hold back the rest
+    Critical,
+}
+
+// ----------------------------------------------------------------------------
+
+impl fmt::Display for QuietMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            QuietMode::Defer => "defer",
+            QuietMode::Silent => "silent",
+            QuietMode::Critical => "critical",
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// The `[quiet]` section of the config.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct QuietConfig {
+    #[serde(default)]
+    pub mode: QuietMode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hours: Vec<QuietHours>,
+}
+
+// ----------------------------------------------------------------------------
+
+/// A window of local time, `end` before `start` runs past midnight.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QuietHours {
+    /// the days the window starts on, every day when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+// ----------------------------------------------------------------------------
+
+impl QuietHours {
+    #[must_use]
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        let starts_on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        let (day, time) = (at.weekday(), at.time());
+
+        if self.start <= self.end {
+            starts_on(day) && self.start <= time && time < self.end
+        } else {
+            (starts_on(day) && time >= self.start) || (starts_on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.days.is_empty() {
+            let days: Vec<String> = self.days.iter().map(Weekday::to_string).collect();
+            write!(f, "{} ", days.join(","))?;
+        }
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+// quiet status ///////////////////////////////////////////////////////////////
+
+/// What happens to a nag that fires while it is quiet.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietEffect {
+    Deferred,
+    Silenced,
+}
+
+// ----------------------------------------------------------------------------
+
+impl fmt::Display for QuietEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            QuietEffect::Deferred => "deferred",
+            QuietEffect::Silenced => "silenced",
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Do not disturb along with nagd's quiet hours, so clients can tell which
+/// nags are affected.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct QuietStatus {
+    pub dnd: bool,
+    /// when do not disturb turns itself off, it stays on until turned off
+    /// without one
+    #[serde(default)]
+    pub dnd_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub quiet: QuietConfig,
+    /// whether it is quiet now on nagd's clock, which is the one quiet hours
+    /// go by
+    #[serde(default)]
+    pub quiet_now: bool,
+    /// what being quiet does to each pending nag when it comes due, by id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub effects: BTreeMap<u64, QuietEffect>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl QuietStatus {
+    #[must_use]
+    pub fn is_dnd(&self, at: DateTime<Utc>) -> bool {
+        self.dnd && self.dnd_until.is_none_or(|until| at < until)
+    }
+
+    #[must_use]
+    pub fn is_quiet(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&Local).naive_local();
+        self.is_dnd(at) || self.quiet.hours.iter().any(|hours| hours.contains(local))
+    }
+
+    /// What being quiet does to the nag firing at `at`, if anything.
+    #[must_use]
+    pub fn effect(&self, nag: &Nag, at: DateTime<Utc>) -> Option<QuietEffect> {
+        if !self.is_quiet(at) {
+            return None;
+        }
+
+        match self.quiet.mode {
+            QuietMode::Defer => Some(QuietEffect::Deferred),
+            QuietMode::Silent => Some(QuietEffect::Silenced),
+            QuietMode::Critical if nag.priority == Priority::Critical => None,
+            QuietMode::Critical => Some(QuietEffect::Deferred),
+        }
+    }
+}
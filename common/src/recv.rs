@@ -5,7 +5,9 @@
 
 use crate::command::Command;
 use crate::response::Response;
+use crate::MAX_MESSAGE_LEN;
 
+use std::io;
 use tokio::io::AsyncReadExt;
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -13,10 +15,30 @@ use tokio::io::AsyncReadExt;
 pub async fn recv_message<R>(
     reader: &mut R,
 ) -> Result<String, Box<dyn std::error::Error + Sync + Send>>
+where
+    R: AsyncReadExt + Unpin,
+{
+    recv_message_within(reader, MAX_MESSAGE_LEN).await
+}
+
+// ----------------------------------------------------------------------------
+
+/// Reads a message of at most `max_len` bytes, for when the sender isn't
+/// trusted with the full `MAX_MESSAGE_LEN` yet.
+pub async fn recv_message_within<R>(
+    reader: &mut R,
+    max_len: u32,
+) -> Result<String, Box<dyn std::error::Error + Sync + Send>>
 where
     R: AsyncReadExt + Unpin,
 {
     let size = reader.read_u32_le().await?;
+    if size > max_len {
+        return Err(Box::new(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes is over the limit", size),
+        )));
+    }
 
     let mut buf = vec![0; size as usize];
     reader.read_exact(&mut buf).await?;
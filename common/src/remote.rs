@@ -0,0 +1,156 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#[cfg(feature = "server")]
+use crate::error_code::ErrorCode;
+#[cfg(feature = "server")]
+use crate::recv::recv_message_within;
+#[cfg(feature = "client")]
+use crate::recv::recv_response;
+use crate::response::Response;
+#[cfg(feature = "server")]
+use crate::send::send_response;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+#[cfg(feature = "client")]
+use tokio::net::TcpStream;
+
+// remote connections /////////////////////////////////////////////////////////
+
+pub type BoxedReader = Box<dyn AsyncRead + Unpin + Send>;
+pub type BoxedWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
+// ----------------------------------------------------------------------------
+
+/// First message on a remote connection, before any `Command`.
+#[derive(Serialize, Deserialize)]
+struct Handshake {
+    token: String,
+}
+
+// ----------------------------------------------------------------------------
+
+/// The largest handshake nagd reads, it comes before the client is trusted.
+pub const MAX_HANDSHAKE_LEN: u32 = 4096;
+
+// ----------------------------------------------------------------------------
+
+#[cfg(feature = "client")]
+pub async fn send_handshake<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    token: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    R: AsyncReadExt + Unpin,
+    W: AsyncWriteExt + Unpin,
+{
+    // framed by hand rather than with send_message so the token isn't logged
+    let message = serde_json::to_string(&Handshake {
+        token: token.to_string(),
+    })?;
+    writer.write_u32_le(message.len() as u32).await?;
+    writer.write_all(message.as_bytes()).await?;
+
+    match recv_response(reader).await? {
+        Response::Ok => Ok(()),
+        Response::Error { code, msg } => Err(format!(
+            "Error! {} ({:?})",
+            msg.as_deref().unwrap_or("No Message"),
+            code
+        )
+        .into()),
+        response => Err(format!("unexpected handshake response {:?}", response).into()),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Reads a client's handshake and answers it, returns whether the token matched.
+#[cfg(feature = "server")]
+pub async fn accept_handshake<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    token: &str,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>
+where
+    R: AsyncReadExt + Unpin,
+    W: AsyncWriteExt + Unpin,
+{
+    let message = recv_message_within(reader, MAX_HANDSHAKE_LEN).await?;
+    let accepted = serde_json::from_str::<Handshake>(&message)
+        .is_ok_and(|handshake| tokens_match(&handshake.token, token));
+
+    let response = if accepted {
+        Response::Ok
+    } else {
+        Response::Error {
+            code: ErrorCode::PermissionDenied,
+            msg: Some("invalid token".to_string()),
+        }
+    };
+    send_response(writer, response).await?;
+
+    Ok(accepted)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(feature = "server")]
+#[must_use]
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    // look at every byte so the time taken doesn't give away how much matched
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// ----------------------------------------------------------------------------
+
+/// Connects to a nagd TCP listener and authenticates, wrapping the connection
+/// in TLS when given the certificate to trust.
+#[cfg(feature = "client")]
+pub async fn connect_tcp(
+    addr: &str,
+    token: &str,
+    tls_ca: Option<&str>,
+) -> Result<(BoxedReader, BoxedWriter), Box<dyn std::error::Error + Send + Sync>> {
+    let stream = TcpStream::connect(addr).await?;
+
+    let (mut reader, mut writer): (BoxedReader, BoxedWriter) = match tls_ca {
+        #[cfg(feature = "tls")]
+        Some(tls_ca) => {
+            let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            let stream = crate::tls::connect(stream, host, tls_ca).await?;
+            let (reader, writer) = tokio::io::split(stream);
+            (Box::new(reader), Box::new(writer))
+        }
+        #[cfg(not(feature = "tls"))]
+        Some(_) => return Err("built without tls support".into()),
+        None => {
+            let (reader, writer) = stream.into_split();
+            (Box::new(reader), Box::new(writer))
+        }
+    };
+
+    send_handshake(&mut reader, &mut writer, token).await?;
+    Ok((reader, writer))
+}
+
+// ----------------------------------------------------------------------------
+
+/// Splits any stream into the boxed halves the handlers work with.
+pub fn split_boxed<S>(stream: S) -> (BoxedReader, BoxedWriter)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, writer) = tokio::io::split(stream);
+    (Box::new(reader), Box::new(writer))
+}
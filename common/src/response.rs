@@ -3,8 +3,12 @@
 // SPDX-License-Identifier: MIT
 //
 
+use crate::alert::Alert;
 use crate::error_code::ErrorCode;
+use crate::history::HistoryEvent;
 use crate::nag::Nag;
+use crate::quiet::QuietStatus;
+use crate::routine::RoutineStatus;
 
 use serde::{Deserialize, Serialize};
 
@@ -18,4 +22,20 @@ pub enum Response {
     NagList {
         nags: Vec<Nag>,
     },
+    AlertList {
+        alerts: Vec<Alert>,
+    },
+    History {
+        events: Vec<HistoryEvent>,
+    },
+    RoutineList {
+        routines: Vec<RoutineStatus>,
+    },
+    Quiet {
+        status: QuietStatus,
+    },
+    NagEvent {
+        // boxed to keep every response, and client errors holding one, small
+        event: Box<HistoryEvent>,
+    },
 }
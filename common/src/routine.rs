@@ -0,0 +1,171 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::{Nag, MAX_REMAINING_SECONDS};
+use crate::priority::Priority;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+// Routine ////////////////////////////////////////////////////////////////////
+
+/// Steps nagd chains one after the other, defined under `[routines.<name>]`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Routine {
+    pub steps: Vec<RoutineStep>,
+    /// whether the next step is timed from a step firing or being dismissed
+    #[serde(default)]
+    pub advance: Advance,
+    /// start over after the last step instead of finishing
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+// ----------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Advance {
+    Fired,
+    #[default]
+    Dismissed,
+}
+
+// ----------------------------------------------------------------------------
+
+/// A nag `minutes` after the previous step, or after the routine started.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RoutineStep {
+    pub name: String,
+    pub minutes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound_file: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+// ----------------------------------------------------------------------------
+
+impl RoutineStep {
+    /// Capped at `MAX_REMAINING_SECONDS` by `Config::validate`.
+    #[must_use]
+    pub fn too_long(&self) -> bool {
+        self.minutes > MAX_REMAINING_SECONDS / 60
+    }
+
+    #[must_use]
+    pub fn nag(&self, from: DateTime<Utc>) -> Nag {
+        Nag {
+            // the config caps minutes, past the last date there is waits forever
+            end_time: i64::try_from(self.minutes)
+                .ok()
+                .and_then(Duration::try_minutes)
+                .and_then(|duration| from.checked_add_signed(duration))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            name: self.name.clone(),
+            sound_file: self.sound_file.clone(),
+            priority: self.priority,
+            ..Default::default()
+        }
+    }
+}
+
+// pomodoro ///////////////////////////////////////////////////////////////////
+
+pub const POMODORO: &str = "pomodoro";
+
+/// The built in `pomodoro` routine, a config can still define its own.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PomodoroConfig {
+    #[serde(default = "default_work_minutes")]
+    pub work_minutes: u64,
+    #[serde(default = "default_short_break_minutes")]
+    pub short_break_minutes: u64,
+    #[serde(default = "default_long_break_minutes")]
+    pub long_break_minutes: u64,
+    /// every how many work periods the break is a long one
+    #[serde(default = "default_long_break_every")]
+    pub long_break_every: u32,
+}
+
+// ----------------------------------------------------------------------------
+
+fn default_work_minutes() -> u64 {
+    25
+}
+
+fn default_short_break_minutes() -> u64 {
+    5
+}
+
+fn default_long_break_minutes() -> u64 {
+    15
+}
+
+fn default_long_break_every() -> u32 {
+    4
+}
+
+// ----------------------------------------------------------------------------
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_minutes: default_work_minutes(),
+            short_break_minutes: default_short_break_minutes(),
+            long_break_minutes: default_long_break_minutes(),
+            long_break_every: default_long_break_every(),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl PomodoroConfig {
+    /// Work and short breaks, with a long break closing every cycle.
+    #[must_use]
+    pub fn routine(&self) -> Routine {
+        let step = |name: &str, minutes| RoutineStep {
+            name: name.to_string(),
+            minutes,
+            ..Default::default()
+        };
+
+        let cycle = self.long_break_every.max(1);
+        let mut steps = Vec::new();
+        for work in 1..=cycle {
+            steps.push(step("Take a break", self.work_minutes));
+            if work < cycle {
+                steps.push(step("Back to work", self.short_break_minutes));
+            } else {
+                steps.push(step(
+                    "Long break over, back to work",
+                    self.long_break_minutes,
+                ));
+            }
+        }
+
+        Routine {
+            steps,
+            advance: Advance::Dismissed,
+            repeat: true,
+        }
+    }
+}
+
+// RoutineStatus //////////////////////////////////////////////////////////////
+
+/// Where a running routine is at, as nagd reports it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RoutineStatus {
+    pub name: String,
+    /// index of the current step
+    pub step: usize,
+    pub steps: usize,
+    /// how many times a repeating routine has gone through all its steps
+    pub cycle: u32,
+    /// the current step's nag, until it fires
+    pub nag: Option<Nag>,
+}
@@ -6,6 +6,7 @@
 use crate::command::Command;
 use crate::response::Response;
 
+use log::debug;
 use tokio::io::AsyncWriteExt;
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -18,7 +19,7 @@ where
     W: AsyncWriteExt + Unpin,
 {
     write_stream.write_u32_le(msg.len() as u32).await?;
-    println!("sending message ({})...", msg);
+    debug!("sending message ({})...", msg);
     write_stream.write_all(msg.as_bytes()).await?;
 
     Ok(())
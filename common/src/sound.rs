@@ -0,0 +1,145 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// sound files ////////////////////////////////////////////////////////////////
+
+/// A sound file that exists and can be read, as an absolute path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SoundFile {
+    pub path: String,
+    /// recognised from the header, `None` when it doesn't look like audio
+    pub format: Option<&'static str>,
+}
+
+// ----------------------------------------------------------------------------
+
+/// Expands a leading `~` and `$VAR` or `${VAR}` anywhere in `path`.
+pub fn expand_path(path: &str) -> Result<String, String> {
+    let path = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = dirs::home_dir().ok_or("no home directory to expand ~ to")?;
+            format!("{}{}", home.display(), rest)
+        }
+        _ => path.to_string(),
+    };
+
+    let mut expanded = String::new();
+    let mut rest = path.as_str();
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| format!("unclosed ${{ in {}", path))?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            let value =
+                std::env::var(name).map_err(|_| format!("${} in {} is not set", name, path))?;
+            expanded.push_str(&value);
+        }
+        rest = after;
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Expands `sound_file`, looks a bare name up in `sounds_dir` (with or without
+/// its extension) and checks the file can be read.
+pub fn resolve_sound_file(sound_file: &str, sounds_dir: Option<&str>) -> Result<SoundFile, String> {
+    let expanded = expand_path(sound_file)?;
+
+    let path = match sounds_dir {
+        Some(sounds_dir) if !expanded.contains('/') => {
+            find_in_dir(&PathBuf::from(expand_path(sounds_dir)?), &expanded)?
+        }
+        _ => PathBuf::from(&expanded),
+    };
+    // nagd doesn't share our working directory
+    let path = std::path::absolute(&path)
+        .map_err(|err| format!("sound file {}: {}", path.display(), err))?;
+
+    if !path.is_file() {
+        return Err(format!("sound file {} does not exist", path.display()));
+    }
+    let mut header = [0; 12];
+    let read = File::open(&path)
+        .and_then(|mut file| file.read(&mut header))
+        .map_err(|err| format!("sound file {} can't be read: {}", path.display(), err))?;
+
+    Ok(SoundFile {
+        path: path.to_string_lossy().into_owned(),
+        format: audio_format(&header[..read]),
+    })
+}
+
+// ----------------------------------------------------------------------------
+
+fn find_in_dir(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let exact = dir.join(name);
+    if exact.exists() {
+        return Ok(exact);
+    }
+
+    let mut found: Vec<PathBuf> = dir
+        .read_dir()
+        .map_err(|err| format!("sounds_dir {}: {}", dir.display(), err))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.file_stem().is_some_and(|stem| stem == name))
+        .collect();
+    found.sort();
+
+    match found.len() {
+        0 => Err(format!("no sound called {} in {}", name, dir.display())),
+        1 => Ok(found.remove(0)),
+        _ => Err(format!(
+            "{} could be any of {}",
+            name,
+            found
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Recognises the common audio containers from the start of a file.
+#[must_use]
+pub fn audio_format(header: &[u8]) -> Option<&'static str> {
+    match header {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("wav"),
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', _, ..] => Some("aiff"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("mp4"),
+        [b'O', b'g', b'g', b'S', ..] => Some("ogg"),
+        [b'f', b'L', b'a', b'C', ..] => Some("flac"),
+        [b'I', b'D', b'3', ..] => Some("mp3"),
+        // an MPEG audio frame sync
+        [0xFF, second, ..] if second & 0xE0 == 0xE0 => Some("mp3"),
+        _ => None,
+    }
+}
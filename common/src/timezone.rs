@@ -0,0 +1,75 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::nag::Nag;
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+
+// timezones //////////////////////////////////////////////////////////////////
+
+/// Looks up an IANA timezone eg: `Asia/Tokyo`.
+pub fn parse_tz(name: &str) -> Result<Tz, String> {
+    name.parse()
+        .map_err(|_| format!("unknown timezone {}", name))
+}
+
+// ----------------------------------------------------------------------------
+
+/// The timezone the nag was set in, if it has a known one.
+#[must_use]
+pub fn nag_tz(nag: &Nag) -> Option<Tz> {
+    nag.tz.as_deref().and_then(|name| parse_tz(name).ok())
+}
+
+// wall clock times ///////////////////////////////////////////////////////////
+
+/// Places a wall clock time in `tz`, times skipped by a DST change move forward
+/// by the size of the gap and repeated times use the first occurrence.
+pub fn resolve_local<T: TimeZone>(tz: &T, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.with_timezone(&Utc),
+        LocalResult::None => {
+            // read it with the offset from before the clocks went forward
+            let before = tz.offset_from_utc_datetime(&(local - Duration::days(1)));
+            let offset = Duration::seconds(before.fix().local_minus_utc().into());
+            Utc.from_utc_datetime(&(local - offset))
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// The first time after `after` that the wall clock in `tz` shows `time`.
+pub fn next_wall_time<T: TimeZone>(tz: &T, time: NaiveTime, after: DateTime<Utc>) -> DateTime<Utc> {
+    let today = after.with_timezone(tz).date_naive();
+    let next = resolve_local(tz, today.and_time(time));
+    if next > after {
+        return next;
+    }
+
+    let tomorrow = today.succ_opt().expect("ran out of dates");
+    resolve_local(tz, tomorrow.and_time(time))
+}
+
+// ----------------------------------------------------------------------------
+
+/// Formats `time` on the wall clock of `tz`, with only as much of the date as
+/// is needed from `now`.
+pub fn format_wall_time<T: TimeZone>(time: &DateTime<Utc>, now: &DateTime<Utc>, tz: &T) -> String
+where
+    T::Offset: fmt::Display,
+{
+    let time = time.with_timezone(tz);
+    let days = (time.date_naive() - now.with_timezone(tz).date_naive()).num_days();
+
+    let format = match days {
+        0 => "%H:%M",
+        1..=6 => "%a %H:%M",
+        _ => "%Y-%m-%d %H:%M",
+    };
+    time.format(format).to_string()
+}
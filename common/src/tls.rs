@@ -0,0 +1,92 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::Arc;
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::CertificateDer;
+
+#[cfg(feature = "client")]
+use tokio::net::TcpStream;
+#[cfg(feature = "client")]
+use tokio_rustls::rustls::pki_types::ServerName;
+#[cfg(feature = "client")]
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+#[cfg(feature = "client")]
+use tokio_rustls::{client, TlsConnector};
+
+#[cfg(feature = "server")]
+use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+#[cfg(feature = "server")]
+use tokio_rustls::rustls::ServerConfig;
+#[cfg(feature = "server")]
+use tokio_rustls::TlsAcceptor;
+
+// pem files //////////////////////////////////////////////////////////////////
+
+fn load_certs(path: &str) -> io::Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::certs(&mut reader).collect()
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(feature = "server")]
+fn load_key(path: &str) -> io::Result<PrivateKeyDer<'static>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::private_key(&mut reader)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no private key in {}", path),
+        )
+    })
+}
+
+// ----------------------------------------------------------------------------
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+// server /////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "server")]
+pub fn acceptor(
+    cert: &str,
+    key: &str,
+) -> Result<TlsAcceptor, Box<dyn std::error::Error + Send + Sync>> {
+    let config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(load_certs(cert)?, load_key(key)?)?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+// client /////////////////////////////////////////////////////////////////////
+
+/// Wraps `stream` in TLS, trusting only the certificates in `ca`.
+#[cfg(feature = "client")]
+pub async fn connect(
+    stream: TcpStream,
+    host: &str,
+    ca: &str,
+) -> Result<client::TlsStream<TcpStream>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca)? {
+        roots.add(cert)?;
+    }
+
+    let config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string())?;
+
+    Ok(TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await?)
+}
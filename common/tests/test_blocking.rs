@@ -0,0 +1,101 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "blocking")]
+
+use common::blocking::{recv_message, send_command, send_message, Client};
+use common::{ClientError, Command, ErrorCode, Nag, NagFilter, Response};
+
+use std::os::unix::net::UnixListener;
+use std::thread;
+use std::time::Duration;
+
+/// Answers one command per connection and hangs up, `None` never answers.
+fn serve_one_shot(listener: UnixListener, answer: fn(Command) -> Option<Response>) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let command = serde_json::from_str(&recv_message(&mut stream).unwrap()).unwrap();
+            match answer(command) {
+                Some(response) => {
+                    let message = serde_json::to_string(&response).unwrap();
+                    send_message(&mut stream, &message).unwrap();
+                }
+                None => thread::sleep(Duration::from_secs(1)),
+            }
+        }
+    });
+}
+
+fn answer(command: Command) -> Option<Response> {
+    Some(match command {
+        Command::ListNags { .. } => Response::NagList {
+            nags: vec![Nag {
+                id: 3,
+                name: "blocking".to_string(),
+                ..Default::default()
+            }],
+        },
+        _ => Response::Error {
+            code: ErrorCode::NotImplemented,
+            msg: None,
+        },
+    })
+}
+
+#[test]
+fn test_blocking_framing() {
+    let command = Command::ClearNags;
+    let mut buf = Vec::new();
+    send_command(&mut buf, &command).unwrap();
+
+    let len = u32::from_le_bytes(buf[..4].try_into().unwrap()) as usize;
+    assert_eq!(len, buf.len() - 4);
+    let sent: Command = serde_json::from_slice(&buf[4..]).unwrap();
+    assert_eq!(sent, command);
+}
+
+#[test]
+fn test_blocking_client() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nag.sock");
+    serve_one_shot(UnixListener::bind(&path).unwrap(), answer);
+
+    let mut client = Client::new(path.to_str().unwrap());
+    for _ in 0..2 {
+        let nags = client.list(NagFilter::default()).unwrap();
+        assert_eq!(nags[0].name, "blocking");
+    }
+
+    assert!(matches!(
+        client.clear(),
+        Err(ClientError::Daemon {
+            code: ErrorCode::NotImplemented,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_blocking_client_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nag.sock");
+    serve_one_shot(UnixListener::bind(&path).unwrap(), |_| None);
+
+    let mut client = Client::new(path.to_str().unwrap()).with_timeout(Duration::from_millis(50));
+    assert!(matches!(client.alerts(), Err(ClientError::Timeout)));
+}
+
+#[test]
+fn test_recv_message_over_the_limit() {
+    let mut data = std::io::Cursor::new(u32::MAX.to_le_bytes().to_vec());
+
+    let err = recv_message(&mut data).unwrap_err();
+    assert!(
+        err.to_string().contains("over the limit"),
+        "unexpected error {}",
+        err
+    );
+}
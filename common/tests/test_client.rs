@@ -0,0 +1,165 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "client")]
+
+use chrono::Utc;
+use common::{
+    recv_command, send_response, Client, ClientError, Command, Endpoint, ErrorCode, EventKind,
+    HistoryEvent, Nag, NagFilter, Response,
+};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::net::UnixListener;
+
+/// Answers one command per connection and hangs up, like nagd used to.
+fn serve_one_shot(listener: UnixListener, connections: Arc<AtomicUsize>) {
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            connections.fetch_add(1, Ordering::SeqCst);
+
+            let response = match recv_command(&mut stream).await.unwrap() {
+                Command::ListNags { .. } => Response::NagList {
+                    nags: vec![Nag {
+                        id: 7,
+                        name: "listed".to_string(),
+                        ..Default::default()
+                    }],
+                },
+                Command::AddNag { .. } => Response::Ok,
+                Command::WaitNag { id: 7 } => Response::NagEvent {
+                    event: Box::new(HistoryEvent {
+                        time: Utc::now(),
+                        kind: EventKind::Fired,
+                        nag: Nag {
+                            id: 7,
+                            ..Default::default()
+                        },
+                    }),
+                },
+                // as if nagd was restarted while the client waited
+                Command::WaitNag { .. } => continue,
+                _ => Response::Error {
+                    code: ErrorCode::NotImplemented,
+                    msg: Some("nope".to_string()),
+                },
+            };
+            send_response(&mut stream, response).await.unwrap();
+        }
+    });
+}
+
+fn client_for(dir: &tempfile::TempDir) -> (Client, Arc<AtomicUsize>) {
+    let path = dir.path().join("nag.sock");
+    let connections = Arc::new(AtomicUsize::new(0));
+    serve_one_shot(UnixListener::bind(&path).unwrap(), Arc::clone(&connections));
+
+    let endpoint = Endpoint::Local(path.to_str().unwrap().to_string());
+    (Client::new(endpoint), connections)
+}
+
+#[tokio::test]
+async fn test_client_typed_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut client, _) = client_for(&dir);
+
+    let nags = client.list(NagFilter::default()).await.unwrap();
+    assert_eq!(nags.len(), 1);
+    assert_eq!(nags[0].id, 7);
+
+    assert!(client.add(Nag::default()).await.is_ok());
+
+    // nagd's errors come back as errors rather than responses
+    match client.alerts().await {
+        Err(ClientError::Daemon { code, msg }) => {
+            assert_eq!(code, ErrorCode::NotImplemented);
+            assert_eq!(msg.as_deref(), Some("nope"));
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[tokio::test]
+async fn test_client_reconnects() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut client, connections) = client_for(&dir);
+
+    for _ in 0..3 {
+        let nags = client.list(NagFilter::default()).await.unwrap();
+        assert_eq!(nags[0].name, "listed");
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_client_connect_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing.sock");
+    let mut client = Client::new(Endpoint::Local(path.to_str().unwrap().to_string()));
+
+    assert!(matches!(
+        client.list(NagFilter::default()).await,
+        Err(ClientError::Transport(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_client_wait() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut client, _) = client_for(&dir);
+
+    let event = client.wait(7).await.unwrap();
+    assert_eq!(event.kind, EventKind::Fired);
+    assert_eq!(event.nag.id, 7);
+}
+
+#[tokio::test]
+async fn test_client_wait_lost() {
+    let dir = tempfile::tempdir().unwrap();
+    let (mut client, connections) = client_for(&dir);
+
+    // a wait is never sent again on a fresh connection
+    let err = client.wait(8).await.unwrap_err();
+    assert!(err.is_disconnected(), "unexpected error {:?}", err);
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_client_never_sends_changes_twice() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nag.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let adds = Arc::new(AtomicUsize::new(0));
+
+    // answers lists, takes adds in and goes away before answering them, as if
+    // nagd was restarted just after running one
+    let counted = Arc::clone(&adds);
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            while let Ok(command) = recv_command(&mut stream).await {
+                if let Command::AddNag { .. } = command {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    break;
+                }
+                let response = Response::NagList { nags: vec![] };
+                send_response(&mut stream, response).await.unwrap();
+            }
+        }
+    });
+
+    let mut client = Client::new(Endpoint::Local(path.to_str().unwrap().to_string()));
+    client.list(NagFilter::default()).await.unwrap();
+    let result = client.add(Nag::default()).await;
+
+    assert!(
+        matches!(result, Err(ClientError::Transport(_))),
+        "{:?}",
+        result
+    );
+    assert_eq!(adds.load(Ordering::SeqCst), 1);
+}
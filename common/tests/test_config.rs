@@ -0,0 +1,390 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "config")]
+
+use chrono::{NaiveTime, Weekday};
+use common::{Advance, Config, EventKind, Nag, Priority, QuietMode, POMODORO};
+
+#[test]
+fn test_default_config_round_trip() {
+    let toml_string = toml::to_string(&Config::default()).unwrap();
+    let config: Config = toml::from_str(&toml_string).unwrap();
+
+    assert_eq!(config.nag_tool_for(Priority::Normal), config.nag_tool);
+    assert_eq!(
+        config.nag_tool_for(Priority::Critical),
+        ["i3-nagbar", "-t", "error", "-m"]
+    );
+    assert_eq!(config.priority(Priority::Critical).sound_repeat, Some(0));
+}
+
+#[test]
+fn test_config_without_priorities() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.audio_tool_for(Priority::High), ["aplay"]);
+    assert_eq!(config.nag_tool_for(Priority::Normal), ["notify-send"]);
+}
+
+#[test]
+fn test_config_with_http() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [http]
+        listen = "127.0.0.1:7879"
+        token = "secret"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.http.listen.as_deref(), Some("127.0.0.1:7879"));
+    assert_eq!(config.http.token.as_deref(), Some("secret"));
+    assert!(config.remote.listen.is_none());
+}
+
+#[test]
+fn test_config_with_routines() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [pomodoro]
+        work_minutes = 50
+
+        [routines.tea]
+        advance = "fired"
+
+        [[routines.tea.steps]]
+        name = "Steeped"
+        minutes = 4
+
+        [[routines.tea.steps]]
+        name = "Drink your tea"
+        minutes = 10
+        "#,
+    )
+    .unwrap();
+
+    let tea = config.routine("tea").unwrap();
+    assert_eq!(tea.advance, Advance::Fired);
+    assert!(!tea.repeat);
+    assert_eq!(tea.steps[1].name, "Drink your tea");
+
+    let pomodoro = config.routine(POMODORO).unwrap();
+    assert_eq!(pomodoro.steps[0].minutes, 50);
+    assert!(config.routine("coffee").is_none());
+    assert_eq!(config.routine_names(), ["tea", POMODORO]);
+}
+
+#[test]
+fn test_config_with_presets() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [presets.tea]
+        name = "Tea is ready"
+        duration = "4m"
+        priority = "high"
+        tags = ["kitchen"]
+
+        [presets.standup]
+        "#,
+    )
+    .unwrap();
+
+    assert!(config.validate().is_ok());
+    let tea = &config.presets["tea"];
+    assert_eq!(tea.duration.as_deref(), Some("4m"));
+    assert_eq!(tea.priority, Some(Priority::High));
+    assert_eq!(tea.tags, ["kitchen"]);
+    assert!(config.presets["standup"].name.is_none());
+}
+
+#[test]
+fn test_config_with_hooks() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [hooks]
+        timeout_seconds = 3
+        fired = [["logger", "-t", "nag"], ["playerctl", "pause"]]
+        "#,
+    )
+    .unwrap();
+
+    assert!(config.validate().is_ok());
+    assert_eq!(config.hooks.timeout_seconds, 3);
+    assert_eq!(config.hooks.max_concurrent, 4);
+    assert_eq!(config.hooks.for_event(EventKind::Fired).len(), 2);
+    assert!(config.hooks.for_event(EventKind::Dismissed).is_empty());
+
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [hooks]
+        snoozed = [[]]
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("hooks.snoozed"), "unexpected error {}", err);
+}
+
+#[test]
+fn test_config_with_tools() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["i3-nagbar", "-m"]
+        audio_tool = ["aplay"]
+
+        [tools]
+        notify = ["notify-send", "nag"]
+        dialog = ["zenity", "--info", "--text"]
+
+        [presets.airport]
+        duration = "2h"
+        nag_tool = "dialog"
+        "#,
+    )
+    .unwrap();
+
+    assert!(config.validate().is_ok());
+    assert_eq!(
+        config.tool("notify"),
+        Some(&["notify-send", "nag"].map(String::from)[..])
+    );
+    assert_eq!(config.tool("rm"), None);
+
+    let mut nag = Nag {
+        nag_tool: config.presets["airport"].nag_tool.clone(),
+        ..Default::default()
+    };
+    assert!(config.check_tools(&nag).is_ok());
+    nag.audio_tool = Some("rm".to_string());
+    let err = config.check_tools(&nag).unwrap_err();
+    assert!(err.contains("rm"), "unexpected error {}", err);
+
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [presets.tea]
+        audio_tool = "speaker"
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("speaker"), "unexpected error {}", err);
+}
+
+#[test]
+fn test_config_rejects_zero_escalation_interval() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [priorities.high.escalation]
+        interval_minutes = 0
+        max_count = 3
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("priorities.high"), "unexpected error {}", err);
+}
+
+#[test]
+fn test_config_rejects_empty_tools() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = []
+        audio_tool = ["aplay"]
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("nag_tool"), "unexpected error {}", err);
+
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [priorities.critical]
+        nag_tool = []
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(
+        err.contains("priorities.critical.nag_tool"),
+        "unexpected error {}",
+        err
+    );
+
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [priorities.high]
+        escalation_tool = []
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(
+        err.contains("priorities.high.escalation_tool"),
+        "unexpected error {}",
+        err
+    );
+}
+
+#[test]
+fn test_config_with_quiet_hours() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [quiet]
+        mode = "critical"
+
+        [[quiet.hours]]
+        days = ["sat", "Sunday"]
+        start = "22:30"
+        end = "08:00"
+        "#,
+    )
+    .unwrap();
+
+    let hours = &config.quiet.hours[0];
+    assert_eq!(config.quiet.mode, QuietMode::Critical);
+    assert_eq!(hours.days, [Weekday::Sat, Weekday::Sun]);
+    assert_eq!(hours.start, NaiveTime::from_hms_opt(22, 30, 0).unwrap());
+    assert_eq!(hours.end, NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+}
+
+#[test]
+fn test_config_session_env() {
+    let vars = [
+        ("DISPLAY", ":1"),
+        ("WAYLAND_DISPLAY", "wayland-1"),
+        ("HOME", "/home/me"),
+        ("MPD_HOST", "localhost"),
+    ]
+    .map(|(name, value)| (name.to_string(), value.to_string()));
+
+    let config = Config::default();
+    let env = config.filter_session_env(vars.clone());
+    assert_eq!(
+        env.keys().collect::<Vec<_>>(),
+        ["DISPLAY", "WAYLAND_DISPLAY"]
+    );
+
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+        session_env = ["DISPLAY", "MPD_HOST"]
+        "#,
+    )
+    .unwrap();
+    let env = config.filter_session_env(vars);
+    assert_eq!(env["DISPLAY"], ":1");
+    assert_eq!(env["MPD_HOST"], "localhost");
+    assert_eq!(env.len(), 2);
+}
+
+#[test]
+fn test_config_with_bad_preset() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [presets.tea]
+        duration = "forever"
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("preset tea"), "unexpected error {}", err);
+}
+
+#[test]
+fn test_config_rejects_long_routine_steps() {
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [[routines.tea.steps]]
+        name = "Steeped"
+        minutes = 9223372036854775807
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("routine tea"), "unexpected error {}", err);
+
+    let config: Config = toml::from_str(
+        r#"
+        edit_tool = ["vim"]
+        nag_tool = ["notify-send"]
+        audio_tool = ["aplay"]
+
+        [pomodoro]
+        long_break_minutes = 9223372036854775807
+        "#,
+    )
+    .unwrap();
+
+    let err = config.validate().unwrap_err();
+    assert!(err.contains("routine pomodoro"), "unexpected error {}", err);
+}
@@ -0,0 +1,74 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "proto")]
+
+use common::{find_cycle, Anchor, Dependency, Nag};
+
+fn nag_after(id: u64, parent: Option<u64>) -> Nag {
+    Nag {
+        id,
+        depends_on: parent.map(|parent| Dependency {
+            parent,
+            anchor: Anchor::Fired,
+            offset_seconds: 60,
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_dependency_column_round_trip() {
+    let depends_on = Dependency {
+        parent: 12,
+        anchor: Anchor::Before,
+        offset_seconds: 90,
+    };
+
+    assert_eq!(depends_on.to_column(), "12/before/90");
+    assert_eq!(Dependency::from_column("12/before/90"), Ok(depends_on));
+    assert!(Dependency::from_column("12/later/90").is_err());
+    assert!(Dependency::from_column("12/fired").is_err());
+}
+
+#[test]
+fn test_dependency_display() {
+    let depends_on = Dependency {
+        parent: 4,
+        anchor: Anchor::Dismissed,
+        offset_seconds: 300,
+    };
+
+    assert_eq!(depends_on.to_string(), "5m after 4 is dismissed");
+    assert!(depends_on.waits());
+}
+
+#[test]
+fn test_find_cycle() {
+    let chain = [
+        nag_after(1, None),
+        nag_after(2, Some(1)),
+        nag_after(3, Some(2)),
+    ];
+    assert_eq!(find_cycle(&chain), None);
+
+    let looped = [
+        nag_after(1, Some(3)),
+        nag_after(2, Some(1)),
+        nag_after(3, Some(2)),
+    ];
+    assert_eq!(find_cycle(&looped), Some(1));
+
+    let own_parent = [nag_after(5, Some(5))];
+    assert_eq!(find_cycle(&own_parent), Some(5));
+
+    // leading into a loop without being part of it
+    let tail = [
+        nag_after(1, Some(2)),
+        nag_after(2, Some(3)),
+        nag_after(3, Some(2)),
+    ];
+    assert_eq!(find_cycle(&tail), Some(2));
+}
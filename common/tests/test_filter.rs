@@ -0,0 +1,100 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "proto")]
+
+use chrono::{Duration, Utc};
+use common::{Nag, NagFilter};
+
+fn tagged_nag(id: u64, name: &str, tags: &[&str], minutes: i64) -> Nag {
+    Nag {
+        id,
+        end_time: Utc::now() + Duration::minutes(minutes),
+        name: name.to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_empty_filter_matches_everything() {
+    let filter = NagFilter::default();
+    assert!(filter.is_empty());
+
+    let matcher = filter.matcher().unwrap();
+    assert!(matcher.matches(&tagged_nag(1, "Standup", &[], 5)));
+}
+
+#[test]
+fn test_filter_by_tag_and_name() {
+    let standup = tagged_nag(1, "Standup", &["work", "meeting"], 5);
+    let laundry = tagged_nag(2, "Laundry", &["home"], 30);
+    let review = tagged_nag(3, "Code review", &["work"], 90);
+
+    let filter = NagFilter {
+        tags: vec!["work".to_string()],
+        ..Default::default()
+    };
+    let matcher = filter.matcher().unwrap();
+    assert!(matcher.matches(&standup));
+    assert!(!matcher.matches(&laundry));
+    assert!(matcher.matches(&review));
+
+    let filter = NagFilter {
+        tags: vec!["work".to_string(), "meeting".to_string()],
+        name: Some("STAND".to_string()),
+        ..Default::default()
+    };
+    let matcher = filter.matcher().unwrap();
+    assert!(matcher.matches(&standup));
+    assert!(!matcher.matches(&review));
+}
+
+#[test]
+fn test_filter_by_regex_ids_and_window() {
+    let standup = tagged_nag(1, "Standup", &[], 5);
+    let review = tagged_nag(3, "Code review", &[], 90);
+
+    let filter = NagFilter {
+        name_regex: Some("^Code".to_string()),
+        ..Default::default()
+    };
+    let matcher = filter.matcher().unwrap();
+    assert!(!matcher.matches(&standup));
+    assert!(matcher.matches(&review));
+
+    let filter = NagFilter {
+        ids: vec![1],
+        ..Default::default()
+    };
+    let matcher = filter.matcher().unwrap();
+    assert!(matcher.matches(&standup));
+    assert!(!matcher.matches(&review));
+
+    let filter = NagFilter {
+        before: Some(Utc::now() + Duration::hours(1)),
+        ..Default::default()
+    };
+    let matcher = filter.matcher().unwrap();
+    assert!(matcher.matches(&standup));
+    assert!(!matcher.matches(&review));
+
+    let filter = NagFilter {
+        after: Some(Utc::now() + Duration::hours(1)),
+        ..Default::default()
+    };
+    let matcher = filter.matcher().unwrap();
+    assert!(!matcher.matches(&standup));
+    assert!(matcher.matches(&review));
+}
+
+#[test]
+fn test_invalid_regex() {
+    let filter = NagFilter {
+        name_regex: Some("(".to_string()),
+        ..Default::default()
+    };
+    assert!(filter.matcher().is_err());
+}
@@ -0,0 +1,75 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "proto")]
+
+use chrono::{Duration, Utc};
+use common::history::{read_events, write_event};
+use common::{EventKind, HistoryEvent, HistoryFilter, Nag, HISTORY_LIMIT};
+use std::io::Cursor;
+
+fn event(kind: EventKind, name: &str, hours_ago: i64) -> HistoryEvent {
+    HistoryEvent {
+        time: Utc::now() - Duration::hours(hours_ago),
+        kind,
+        nag: Nag {
+            id: 1,
+            end_time: Utc::now(),
+            name: name.to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+#[test]
+fn test_write_and_read_events() {
+    let events = vec![
+        event(EventKind::Created, "Pizza rolls", 2),
+        event(EventKind::Fired, "Pizza rolls", 1),
+        event(EventKind::Dismissed, "Pizza rolls", 0),
+    ];
+
+    let mut buffer = Vec::new();
+    for event in &events {
+        write_event(event, &mut buffer).expect("Failed to write event");
+    }
+    buffer.extend_from_slice(b"not json\n\n");
+
+    let read = read_events(Cursor::new(buffer)).expect("Failed to read events");
+    assert_eq!(read, events);
+}
+
+#[test]
+fn test_history_filter() {
+    let old = event(EventKind::Fired, "Pizza rolls", 48);
+    let recent = event(EventKind::Fired, "Laundry", 1);
+
+    let filter = HistoryFilter {
+        since: Some(Utc::now() - Duration::days(1)),
+        ..Default::default()
+    };
+    assert!(!filter.matches(&old));
+    assert!(filter.matches(&recent));
+
+    let filter = HistoryFilter {
+        until: Some(Utc::now() - Duration::days(1)),
+        name: Some("pizza".to_string()),
+        ..Default::default()
+    };
+    assert!(filter.matches(&old));
+    assert!(!filter.matches(&recent));
+}
+
+#[test]
+fn test_history_filter_limit() {
+    let mut filter = HistoryFilter::default();
+    assert_eq!(filter.limit(), Some(HISTORY_LIMIT));
+
+    filter.since = Some(Utc::now() - Duration::days(1));
+    assert_eq!(filter.limit(), None);
+
+    filter.limit = Some(5);
+    assert_eq!(filter.limit(), Some(5));
+}
@@ -0,0 +1,247 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "ics")]
+
+use chrono::{DateTime, Duration, DurationRound, TimeZone, Utc};
+use common::{nags_from_ics, nags_to_ics, IcsComponent, Nag, Priority};
+
+fn sample_nags() -> Vec<Nag> {
+    let now = Utc::now().duration_trunc(Duration::seconds(1)).unwrap();
+
+    vec![
+        Nag {
+            id: 1,
+            end_time: now + Duration::minutes(5),
+            name: "Pizza rolls, the good ones; with sauce".to_string(),
+            sound_file: Some("/home/me/sounds/campfire.wav".to_string()),
+            priority: Priority::Critical,
+            tags: vec!["kitchen".to_string(), "food,hot".to_string()],
+            ..Default::default()
+        },
+        Nag {
+            id: 2,
+            end_time: now + Duration::hours(3),
+            name: "A very long reminder name that will certainly need to be folded \
+                   across several lines when it is written out"
+                .to_string(),
+            ..Default::default()
+        },
+    ]
+}
+
+fn utc(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+#[test]
+fn test_round_trip_events() {
+    let nags = sample_nags();
+    let ics = nags_to_ics(&nags, IcsComponent::Event);
+
+    assert!(ics.contains("BEGIN:VEVENT"));
+    assert!(ics.contains("BEGIN:VALARM"));
+    assert!(ics
+        .lines()
+        .all(|line| line.trim_end_matches('\r').len() <= 75));
+
+    let imported = nags_from_ics(&ics, Utc::now(), Utc::now() + Duration::days(1)).unwrap();
+    let expected: Vec<Nag> = nags.into_iter().map(|nag| Nag { id: 0, ..nag }).collect();
+    assert_eq!(imported, expected);
+}
+
+#[test]
+fn test_round_trip_todos() {
+    let nags = sample_nags();
+    let ics = nags_to_ics(&nags, IcsComponent::Todo);
+
+    assert!(ics.contains("BEGIN:VTODO"));
+    assert!(ics.contains("DUE:"));
+
+    let imported = nags_from_ics(&ics, Utc::now(), Utc::now() + Duration::days(1)).unwrap();
+    assert_eq!(imported.len(), 2);
+    assert_eq!(imported[0].name, nags[0].name);
+    assert_eq!(imported[0].tags, nags[0].tags);
+    assert_eq!(imported[1].end_time, nags[1].end_time);
+}
+
+#[test]
+fn test_import_window() {
+    let nags = sample_nags();
+    let ics = nags_to_ics(&nags, IcsComponent::Event);
+
+    let imported = nags_from_ics(&ics, Utc::now(), Utc::now() + Duration::hours(1)).unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].priority, Priority::Critical);
+}
+
+#[test]
+fn test_import_alarm_offset_and_tzid() {
+    let ics = "BEGIN:VCALENDAR\r\n\
+               VERSION:2.0\r\n\
+               BEGIN:VEVENT\r\n\
+               DTSTART;TZID=Asia/Tokyo:20241007T090000\r\n\
+               SUMMARY:Standup\r\n\
+               BEGIN:VALARM\r\n\
+               ACTION:DISPLAY\r\n\
+               TRIGGER:-PT15M\r\n\
+               END:VALARM\r\n\
+               BEGIN:VALARM\r\n\
+               ACTION:AUDIO\r\n\
+               ATTACH:file:///tmp/ding.wav\r\n\
+               TRIGGER;VALUE=DATE-TIME:20241006T230000Z\r\n\
+               END:VALARM\r\n\
+               END:VEVENT\r\n\
+               END:VCALENDAR\r\n";
+
+    let from = utc("2024-10-01T00:00:00Z");
+    let nags = nags_from_ics(ics, from, from + Duration::days(30)).unwrap();
+
+    assert_eq!(nags.len(), 2);
+    assert_eq!(nags[0].end_time, utc("2024-10-06T23:00:00Z"));
+    assert_eq!(nags[0].sound_file.as_deref(), Some("/tmp/ding.wav"));
+    assert_eq!(nags[1].end_time, utc("2024-10-06T23:45:00Z"));
+    assert_eq!(nags[1].name, "Standup");
+    assert_eq!(nags[1].sound_file, None);
+    assert_eq!(nags[1].tz.as_deref(), Some("Asia/Tokyo"));
+}
+
+#[test]
+fn test_import_rrule_across_dst() {
+    // New York springs forward on 2024-03-10, the wall clock time must stay 09:00
+    let ics = "BEGIN:VCALENDAR\n\
+               BEGIN:VEVENT\n\
+               DTSTART;TZID=America/New_York:20240309T090000\n\
+               RRULE:FREQ=DAILY;COUNT=3\n\
+               SUMMARY:Tea\n\
+               END:VEVENT\n\
+               END:VCALENDAR\n";
+
+    let from = utc("2024-03-01T00:00:00Z");
+    let nags = nags_from_ics(ics, from, from + Duration::days(30)).unwrap();
+
+    let times: Vec<DateTime<Utc>> = nags.iter().map(|nag| nag.end_time).collect();
+    assert_eq!(
+        times,
+        vec![
+            Utc.with_ymd_and_hms(2024, 3, 9, 14, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 10, 13, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 11, 13, 0, 0).unwrap(),
+        ]
+    );
+}
+
+#[test]
+fn test_import_rrule_until_and_unsupported() {
+    let ics = "BEGIN:VCALENDAR\n\
+               BEGIN:VEVENT\n\
+               DTSTART:20240101T120000Z\n\
+               RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20240201T000000Z\n\
+               SUMMARY:Bins\n\
+               END:VEVENT\n\
+               BEGIN:VTODO\n\
+               DUE:20240105T080000Z\n\
+               RRULE:FREQ=WEEKLY;BYDAY=MO,WE\n\
+               SUMMARY:Plants\n\
+               END:VTODO\n\
+               END:VCALENDAR\n";
+
+    let from = utc("2024-01-01T00:00:00Z");
+    let nags = nags_from_ics(ics, from, from + Duration::days(60)).unwrap();
+
+    let bins: Vec<DateTime<Utc>> = nags
+        .iter()
+        .filter(|nag| nag.name == "Bins")
+        .map(|nag| nag.end_time)
+        .collect();
+    assert_eq!(
+        bins,
+        vec![
+            utc("2024-01-01T12:00:00Z"),
+            utc("2024-01-15T12:00:00Z"),
+            utc("2024-01-29T12:00:00Z"),
+        ]
+    );
+    assert_eq!(nags.iter().filter(|nag| nag.name == "Plants").count(), 1);
+}
+
+#[test]
+fn test_import_malformed() {
+    assert!(nags_from_ics("BEGIN:VCALENDAR\nBEGIN:VEVENT\n", Utc::now(), Utc::now()).is_err());
+    assert!(nags_from_ics("garbage", Utc::now(), Utc::now()).is_err());
+}
+
+#[test]
+fn test_import_rrule_bad_interval() {
+    let rrule = |rule: &str| {
+        format!(
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20240101T120000Z\nRRULE:{}\n\
+             SUMMARY:Bins\nEND:VEVENT\nEND:VCALENDAR\n",
+            rule
+        )
+    };
+    let from = utc("2024-01-01T00:00:00Z");
+    let to = from + Duration::days(60);
+
+    let err = nags_from_ics(&rrule("FREQ=DAILY;INTERVAL=0"), from, to).unwrap_err();
+    assert!(err.contains("INTERVAL=0"), "unexpected error {}", err);
+
+    // steps past the last date there is stop the rule instead of panicking
+    let nags = nags_from_ics(&rrule("FREQ=YEARLY;INTERVAL=4294967295"), from, to).unwrap();
+    assert_eq!(nags.len(), 1);
+    let nags = nags_from_ics(&rrule("FREQ=DAILY;INTERVAL=4294967295"), from, to).unwrap();
+    assert_eq!(nags.len(), 1);
+}
+
+#[test]
+fn test_import_oversized_duration() {
+    let alarm = |trigger: &str| {
+        format!(
+            "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20240101T120000Z\nSUMMARY:Bins\n\
+             BEGIN:VALARM\nTRIGGER:{}\nEND:VALARM\nEND:VEVENT\nEND:VCALENDAR\n",
+            trigger
+        )
+    };
+    let from = utc("2024-01-01T00:00:00Z");
+    let to = from + Duration::days(60);
+
+    for trigger in [
+        "P9999999999999W",
+        "-P9999999999999W",
+        "P999999999999D",
+        "P1W999999999999D",
+    ] {
+        let err = nags_from_ics(&alarm(trigger), from, to).unwrap_err();
+        assert!(err.contains("too long"), "unexpected error {}", err);
+    }
+
+    // fits in a duration, but not once added to the occurrence
+    assert!(nags_from_ics(&alarm("P99999999W"), from, to).is_err());
+    assert!(nags_from_ics(&alarm("-P99999999W"), from, to).is_err());
+}
+
+#[test]
+fn test_import_old_minutely_rule() {
+    let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20200101T120000Z\n\
+               DTEND:20200101T121000Z\nRRULE:FREQ=MINUTELY\nSUMMARY:Stretch\n\
+               BEGIN:VALARM\nTRIGGER;RELATED=END:PT5M\nEND:VALARM\n\
+               END:VEVENT\nEND:VCALENDAR\n";
+    let from = utc("2024-06-01T00:00:00Z");
+    let to = from + Duration::minutes(3);
+
+    // millions of steps before the window, none of them count
+    let nags = nags_from_ics(ics, from, to).unwrap();
+    let times: Vec<DateTime<Utc>> = nags.iter().map(|nag| nag.end_time).collect();
+    assert_eq!(
+        times,
+        (0..=3)
+            .map(|minutes| from + Duration::minutes(minutes))
+            .collect::<Vec<_>>()
+    );
+
+    // COUNT still counts from DTSTART
+    let counted = ics.replace("FREQ=MINUTELY", "FREQ=MINUTELY;COUNT=10");
+    assert!(nags_from_ics(&counted, from, to).unwrap().is_empty());
+}
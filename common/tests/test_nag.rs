@@ -3,8 +3,13 @@
 // SPDX-License-Identifier: MIT
 //
 
-use chrono::{Duration, Utc};
-use common::{nag_to_line, read_nags_from_file, time_remaining, write_nags_to_file, Nag};
+#![cfg(feature = "csv")]
+
+use chrono::{Duration, Local, Utc};
+use common::{
+    format_duration, nag_to_line, read_nags_from_file, time_remaining, write_nags_to_file, Anchor,
+    Dependency, Escalation, Nag, Priority,
+};
 use std::io::{Cursor, Read, Seek, SeekFrom};
 
 #[test]
@@ -18,18 +23,53 @@ fn test_time_remaining() {
     assert_eq!(remaining, "0");
 }
 
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(Duration::seconds(90)), "1m30s");
+    assert_eq!(format_duration(Duration::hours(2)), "2h");
+    assert_eq!(format_duration(Duration::milliseconds(300)), "0s");
+}
+
+#[test]
+fn test_pause_and_resume() {
+    let now = Utc::now();
+    let mut nag = Nag {
+        end_time: now + Duration::minutes(10),
+        name: "Laundry".to_string(),
+        ..Default::default()
+    };
+
+    nag.pause(now);
+    assert!(nag.is_paused());
+    assert_eq!(nag.remaining_seconds, Some(600));
+    assert_eq!(nag.remaining(), "10m");
+
+    // an hour at lunch later the countdown carries on where it stopped
+    let later = now + Duration::hours(1);
+    nag.resume(later);
+    assert!(!nag.is_paused());
+    assert_eq!(nag.end_time, later + Duration::minutes(10));
+
+    // resuming a running nag leaves it be, overdue nags pause with nothing left
+    nag.resume(now);
+    assert_eq!(nag.end_time, later + Duration::minutes(10));
+    nag.pause(later + Duration::hours(1));
+    assert_eq!(nag.remaining_seconds, Some(0));
+}
+
 #[test]
 fn test_nag_to_line() {
     let nag = Nag {
         end_time: Utc::now(),
         name: "Test Nag".to_string(),
         sound_file: Some("test.wav".to_string()),
+        ..Default::default()
     };
 
     let line = nag_to_line(&nag);
     let expected = format!(
         "\"{}\",\"Test Nag\",\"test.wav\"",
-        nag.end_time.to_rfc3339()
+        nag.end_time.with_timezone(&Local).to_rfc3339()
     );
     assert_eq!(line, expected);
 
@@ -37,10 +77,14 @@ fn test_nag_to_line() {
         end_time: Utc::now(),
         name: "Silent Nag".to_string(),
         sound_file: None,
+        ..Default::default()
     };
 
     let line_no_sound = nag_to_line(&nag_no_sound);
-    let expected_no_sound = format!("\"{}\",\"Silent Nag\"", nag_no_sound.end_time.to_rfc3339());
+    let expected_no_sound = format!(
+        "\"{}\",\"Silent Nag\"",
+        nag_no_sound.end_time.with_timezone(&Local).to_rfc3339()
+    );
     assert_eq!(line_no_sound, expected_no_sound);
 }
 
@@ -50,11 +94,13 @@ fn test_write_nags_to_file() {
         end_time: Utc::now(),
         name: "Nag 1".to_string(),
         sound_file: Some("sound1.wav".to_string()),
+        ..Default::default()
     };
     let nag2 = Nag {
         end_time: Utc::now() + Duration::minutes(10),
         name: "Nag 2".to_string(),
         sound_file: None,
+        ..Default::default()
     };
     let nags = vec![nag1.clone(), nag2.clone()];
 
@@ -75,11 +121,13 @@ fn test_read_nags_from_file() {
         end_time: Utc::now(),
         name: "Nag 1".to_string(),
         sound_file: Some("sound1.wav".to_string()),
+        ..Default::default()
     };
     let nag2 = Nag {
         end_time: Utc::now() + Duration::minutes(10),
         name: "Nag 2".to_string(),
         sound_file: None,
+        ..Default::default()
     };
 
     let data = format!("{}\n{}\n", nag_to_line(&nag1), nag_to_line(&nag2));
@@ -91,3 +139,62 @@ fn test_read_nags_from_file() {
     assert_eq!(read_nags[0], nag1);
     assert_eq!(read_nags[1], nag2);
 }
+
+#[test]
+fn test_read_nags_with_extra_columns() {
+    let nag1 = Nag {
+        end_time: Utc::now(),
+        name: "Urgent".to_string(),
+        sound_file: None,
+        priority: Priority::Critical,
+        ..Default::default()
+    };
+    let nag2 = Nag {
+        end_time: Utc::now() + Duration::minutes(10),
+        name: "Whenever".to_string(),
+        sound_file: Some("sound2.wav".to_string()),
+        priority: Priority::Low,
+        id: 7,
+        tags: vec!["home".to_string(), "kitchen".to_string()],
+        escalation: Some(Escalation {
+            interval_minutes: 5,
+            max_count: 2,
+            loop_sound: true,
+            sound_file: Some("/tmp/loud.wav".to_string()),
+        }),
+        depends_on: Some(Dependency {
+            parent: 3,
+            anchor: Anchor::Dismissed,
+            offset_seconds: 300,
+        }),
+        tz: Some("Asia/Tokyo".to_string()),
+        remaining_seconds: Some(90),
+        nag_tool: Some("dialog".to_string()),
+        audio_tool: Some("quiet".to_string()),
+    };
+
+    let line = nag_to_line(&nag1);
+    let expected = format!(
+        "\"{}\",\"Urgent\",\"None\",\"priority=critical\"",
+        nag1.end_time.with_timezone(&Local).to_rfc3339()
+    );
+    assert_eq!(line, expected);
+
+    let data = format!("{}\n{}\n", nag_to_line(&nag1), nag_to_line(&nag2));
+
+    let mut cursor = Cursor::new(data.into_bytes());
+    let read_nags = read_nags_from_file(&mut cursor).expect("Failed to read nags from file");
+
+    assert_eq!(read_nags, vec![nag1, nag2]);
+}
+
+#[test]
+fn test_escalation_needs_an_interval() {
+    assert!(Escalation::from_column("5/2/loop").is_ok());
+    let err = Escalation::from_column("0/3/").unwrap_err();
+    assert!(
+        err.contains("at least a minute"),
+        "unexpected error {}",
+        err
+    );
+}
@@ -0,0 +1,80 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "proto")]
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use common::{Nag, Priority, QuietEffect, QuietHours, QuietMode, QuietStatus};
+
+fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    // 2024-01-01 is a monday
+    NaiveDate::from_ymd_opt(2024, 1, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+fn hours(days: Vec<Weekday>, start: (u32, u32), end: (u32, u32)) -> QuietHours {
+    QuietHours {
+        days,
+        start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+        end: NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap(),
+    }
+}
+
+#[test]
+fn test_quiet_hours_within_a_day() {
+    let lunch = hours(vec![], (12, 0), (13, 0));
+    assert!(lunch.contains(at(1, 12, 0)));
+    assert!(lunch.contains(at(3, 12, 59)));
+    assert!(!lunch.contains(at(1, 13, 0)));
+    assert!(!lunch.contains(at(1, 11, 59)));
+}
+
+#[test]
+fn test_quiet_hours_past_midnight() {
+    // friday night into saturday morning only
+    let night = hours(vec![Weekday::Fri], (22, 0), (7, 0));
+    assert!(night.contains(at(5, 23, 0)));
+    assert!(night.contains(at(6, 6, 59)));
+    assert!(!night.contains(at(6, 7, 0)));
+    assert!(!night.contains(at(6, 23, 0)));
+    assert!(!night.contains(at(5, 6, 0)));
+    assert_eq!(night.to_string(), "Fri 22:00-07:00");
+}
+
+#[test]
+fn test_dnd_effect() {
+    let now = Utc::now();
+    let nag = Nag {
+        end_time: now,
+        name: "stand up".to_string(),
+        ..Default::default()
+    };
+    let critical = Nag {
+        priority: Priority::Critical,
+        ..nag.clone()
+    };
+
+    let mut status = QuietStatus {
+        dnd: true,
+        dnd_until: Some(now + Duration::hours(1)),
+        ..Default::default()
+    };
+    assert_eq!(status.effect(&nag, now), Some(QuietEffect::Deferred));
+    assert_eq!(status.effect(&nag, now + Duration::hours(2)), None);
+
+    status.quiet.mode = QuietMode::Silent;
+    assert_eq!(status.effect(&critical, now), Some(QuietEffect::Silenced));
+
+    status.quiet.mode = QuietMode::Critical;
+    assert_eq!(status.effect(&nag, now), Some(QuietEffect::Deferred));
+    assert_eq!(status.effect(&critical, now), None);
+
+    status.dnd_until = None;
+    assert!(status.is_dnd(now + Duration::days(365)));
+    status.dnd = false;
+    assert!(!status.is_quiet(now));
+}
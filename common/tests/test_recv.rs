@@ -3,7 +3,10 @@
 // SPDX-License-Identifier: MIT
 //
 
+#![cfg(feature = "async")]
+
 use common::command::Command;
+use common::filter::NagFilter;
 use common::response::Response;
 use common::{recv_command, recv_message, recv_response};
 use tokio::io::{self, AsyncWriteExt};
@@ -26,7 +29,9 @@ async fn test_recv_message() {
 async fn test_recv_command() {
     let (mut write_stream, mut read_stream) = io::duplex(128);
 
-    let command = Command::ListNags;
+    let command = Command::ListNags {
+        filter: NagFilter::default(),
+    };
 
     // Write the serialized command to the stream
     let serialized_command = serde_json::to_string(&command).unwrap();
@@ -65,3 +70,18 @@ async fn test_recv_response() {
     let received_response = recv_response(&mut read_stream).await.unwrap();
     assert_eq!(received_response, response);
 }
+
+#[tokio::test]
+async fn test_recv_message_over_the_limit() {
+    let (mut write_stream, mut read_stream) = io::duplex(64);
+
+    // only the length, nothing should be allocated for it
+    write_stream.write_u32_le(u32::MAX).await.unwrap();
+
+    let err = recv_message(&mut read_stream).await.unwrap_err();
+    assert!(
+        err.to_string().contains("over the limit"),
+        "unexpected error {}",
+        err
+    );
+}
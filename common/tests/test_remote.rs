@@ -0,0 +1,135 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(all(feature = "client", feature = "server"))]
+
+use common::command::Command;
+use common::filter::NagFilter;
+use common::recv::{recv_command, recv_response};
+use common::remote::{
+    accept_handshake, connect_tcp, split_boxed, BoxedReader, BoxedWriter, MAX_HANDSHAKE_LEN,
+};
+use common::response::Response;
+use common::send::{send_command, send_response};
+use common::Nag;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+const TOKEN: &str = "s3cret";
+
+/// Answers one connection the way nagd does, returning whether the token matched.
+async fn serve(mut reader: BoxedReader, mut writer: BoxedWriter) -> bool {
+    if !accept_handshake(&mut reader, &mut writer, TOKEN)
+        .await
+        .unwrap()
+    {
+        return false;
+    }
+
+    let command = recv_command(&mut reader).await.unwrap();
+    assert!(matches!(command, Command::ListNags { .. }));
+    let nags = vec![Nag {
+        id: 1,
+        name: "remote".to_string(),
+        ..Default::default()
+    }];
+    send_response(&mut writer, Response::NagList { nags })
+        .await
+        .unwrap();
+    true
+}
+
+async fn serve_plain() -> (String, JoinHandle<bool>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, writer) = split_boxed(stream);
+        serve(reader, writer).await
+    });
+
+    (addr, handle)
+}
+
+async fn list_nags(mut reader: BoxedReader, mut writer: BoxedWriter) -> Response {
+    let command = Command::ListNags {
+        filter: NagFilter::default(),
+    };
+    send_command(&mut writer, command).await.unwrap();
+    recv_response(&mut reader).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_remote_round_trip() {
+    let (addr, handle) = serve_plain().await;
+
+    let (reader, writer) = connect_tcp(&addr, TOKEN, None).await.unwrap();
+    match list_nags(reader, writer).await {
+        Response::NagList { nags } => assert_eq!(nags[0].name, "remote"),
+        response => panic!("unexpected response {:?}", response),
+    }
+    assert!(handle.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_remote_bad_token() {
+    let (addr, handle) = serve_plain().await;
+
+    let err = connect_tcp(&addr, "wrong", None)
+        .await
+        .err()
+        .expect("bad token was accepted");
+    assert!(err.to_string().contains("invalid token"));
+    assert!(!handle.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_remote_oversized_handshake() {
+    let (mut client, server) = tokio::io::duplex(64);
+    let (reader, writer) = tokio::io::split(server);
+    let (mut reader, mut writer): (BoxedReader, BoxedWriter) = (Box::new(reader), Box::new(writer));
+
+    // only the length goes out, nagd shouldn't wait around for the rest
+    client.write_u32_le(MAX_HANDSHAKE_LEN + 1).await.unwrap();
+    let err = accept_handshake(&mut reader, &mut writer, TOKEN)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("over the limit"));
+}
+
+#[cfg(feature = "tls")]
+#[tokio::test]
+async fn test_remote_tls_round_trip() {
+    use std::io::Write;
+
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let mut cert_file = tempfile::NamedTempFile::new().unwrap();
+    cert_file.write_all(cert.cert.pem().as_bytes()).unwrap();
+    let mut key_file = tempfile::NamedTempFile::new().unwrap();
+    key_file
+        .write_all(cert.key_pair.serialize_pem().as_bytes())
+        .unwrap();
+
+    let cert_path = cert_file.path().to_str().unwrap();
+    let acceptor = common::tls::acceptor(cert_path, key_file.path().to_str().unwrap()).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, writer) = split_boxed(acceptor.accept(stream).await.unwrap());
+        serve(reader, writer).await
+    });
+
+    let addr = format!("localhost:{}", port);
+    let (reader, writer) = connect_tcp(&addr, TOKEN, Some(cert_path)).await.unwrap();
+    match list_nags(reader, writer).await {
+        Response::NagList { nags } => assert_eq!(nags[0].name, "remote"),
+        response => panic!("unexpected response {:?}", response),
+    }
+    assert!(handle.await.unwrap());
+}
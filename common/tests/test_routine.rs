@@ -0,0 +1,69 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "proto")]
+
+use chrono::{DateTime, Duration, Utc};
+use common::{Advance, PomodoroConfig, Priority, RoutineStep};
+
+#[test]
+fn test_pomodoro_routine() {
+    let pomodoro = PomodoroConfig {
+        long_break_every: 2,
+        ..Default::default()
+    };
+    let routine = pomodoro.routine();
+
+    let minutes: Vec<u64> = routine.steps.iter().map(|step| step.minutes).collect();
+    assert_eq!(minutes, [25, 5, 25, 15]);
+    assert_eq!(routine.advance, Advance::Dismissed);
+    assert!(routine.repeat);
+}
+
+#[test]
+fn test_pomodoro_without_short_breaks() {
+    let pomodoro = PomodoroConfig {
+        long_break_every: 0,
+        ..Default::default()
+    };
+
+    let minutes: Vec<u64> = pomodoro
+        .routine()
+        .steps
+        .iter()
+        .map(|step| step.minutes)
+        .collect();
+    assert_eq!(minutes, [25, 15]);
+}
+
+#[test]
+fn test_routine_step_nag() {
+    let step = RoutineStep {
+        name: "Drink your tea".to_string(),
+        minutes: 10,
+        sound_file: Some("kettle.wav".to_string()),
+        priority: Priority::High,
+    };
+    let from = Utc::now();
+
+    let nag = step.nag(from);
+    assert_eq!(nag.end_time, from + Duration::minutes(10));
+    assert_eq!(nag.name, "Drink your tea");
+    assert_eq!(nag.sound_file.as_deref(), Some("kettle.wav"));
+    assert_eq!(nag.priority, Priority::High);
+    assert_eq!(nag.id, 0);
+}
+
+#[test]
+fn test_routine_step_nag_saturates() {
+    let step = RoutineStep {
+        name: "Forever".to_string(),
+        minutes: u64::MAX,
+        ..Default::default()
+    };
+
+    assert!(step.too_long());
+    assert_eq!(step.nag(Utc::now()).end_time, DateTime::<Utc>::MAX_UTC);
+}
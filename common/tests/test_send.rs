@@ -3,7 +3,10 @@
 // SPDX-License-Identifier: MIT
 //
 
+#![cfg(feature = "async")]
+
 use common::command::Command;
+use common::filter::NagFilter;
 use common::response::Response;
 use common::send::{send_command, send_message, send_response};
 
@@ -32,7 +35,9 @@ async fn test_send_message() {
 async fn test_send_command() {
     let (mut write_stream, mut read_stream) = io::duplex(128);
 
-    let command = Command::ListNags;
+    let command = Command::ListNags {
+        filter: NagFilter::default(),
+    };
 
     let send_result = send_command(&mut write_stream, command.clone()).await;
     assert!(send_result.is_ok());
@@ -0,0 +1,74 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "config")]
+
+use common::{audio_format, expand_path, resolve_sound_file};
+use std::fs;
+
+const WAV_HEADER: &[u8] = b"RIFF\x24\x00\x00\x00WAVEfmt ";
+
+#[test]
+fn test_expand_path() {
+    std::env::set_var("NAG_TEST_SOUNDS", "/srv/sounds");
+    let home = dirs::home_dir().unwrap();
+
+    assert_eq!(
+        expand_path("~/ding.wav").unwrap(),
+        format!("{}/ding.wav", home.display())
+    );
+    assert_eq!(
+        expand_path("$NAG_TEST_SOUNDS/ding.wav").unwrap(),
+        "/srv/sounds/ding.wav"
+    );
+    assert_eq!(
+        expand_path("${NAG_TEST_SOUNDS}x/$").unwrap(),
+        "/srv/soundsx/$"
+    );
+    assert_eq!(expand_path("~user/ding.wav").unwrap(), "~user/ding.wav");
+    assert!(expand_path("$NAG_TEST_UNSET_VARIABLE/ding.wav").is_err());
+    assert!(expand_path("${NAG_TEST_SOUNDS/ding.wav").is_err());
+}
+
+#[test]
+fn test_resolve_sound_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let sounds_dir = dir.path().to_str().unwrap();
+    fs::write(dir.path().join("campfire.wav"), WAV_HEADER).unwrap();
+    fs::write(dir.path().join("notes.txt"), "not a sound").unwrap();
+    fs::write(dir.path().join("bell.ogg"), b"OggS").unwrap();
+    fs::write(dir.path().join("bell.wav"), WAV_HEADER).unwrap();
+
+    let campfire = format!("{}/campfire.wav", sounds_dir);
+    for name in ["campfire", "campfire.wav", campfire.as_str()] {
+        let sound = resolve_sound_file(name, Some(sounds_dir)).unwrap();
+        assert_eq!(sound.path, campfire);
+        assert_eq!(sound.format, Some("wav"));
+    }
+
+    let notes = resolve_sound_file("notes", Some(sounds_dir)).unwrap();
+    assert_eq!(notes.format, None);
+
+    let err = resolve_sound_file("bell", Some(sounds_dir)).unwrap_err();
+    assert!(err.contains("could be any of"), "unexpected error {}", err);
+    let err = resolve_sound_file("campfyre", Some(sounds_dir)).unwrap_err();
+    assert!(err.contains("no sound called"), "unexpected error {}", err);
+    let missing = format!("{}/missing.wav", sounds_dir);
+    let err = resolve_sound_file(&missing, None).unwrap_err();
+    assert!(err.contains("does not exist"), "unexpected error {}", err);
+}
+
+#[test]
+fn test_audio_format() {
+    assert_eq!(audio_format(WAV_HEADER), Some("wav"));
+    assert_eq!(audio_format(b"OggS\x00\x02"), Some("ogg"));
+    assert_eq!(audio_format(b"fLaC"), Some("flac"));
+    assert_eq!(audio_format(b"ID3\x04"), Some("mp3"));
+    assert_eq!(audio_format(&[0xFF, 0xFB, 0x90]), Some("mp3"));
+    assert_eq!(audio_format(b"\x00\x00\x00\x20ftypM4A "), Some("mp4"));
+    assert_eq!(audio_format(b"FORM\x00\x00\x00\x00AIFF"), Some("aiff"));
+    assert_eq!(audio_format(b"#!/bin/sh"), None);
+    assert_eq!(audio_format(b""), None);
+}
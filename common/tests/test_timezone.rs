@@ -0,0 +1,101 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+#![cfg(feature = "tz")]
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use common::{format_wall_time, nag_tz, next_wall_time, parse_tz, resolve_local, Nag};
+
+fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+#[test]
+fn test_resolve_skipped_time() {
+    // New York springs forward from 02:00 to 03:00 on 2024-03-10
+    let new_york = parse_tz("America/New_York").unwrap();
+    assert_eq!(
+        resolve_local(&new_york, local(2024, 3, 10, 2, 30)),
+        Utc.with_ymd_and_hms(2024, 3, 10, 7, 30, 0).unwrap()
+    );
+
+    // Lord Howe Island only moves by half an hour, on 2024-10-06 at 02:00
+    let lord_howe = parse_tz("Australia/Lord_Howe").unwrap();
+    assert_eq!(
+        resolve_local(&lord_howe, local(2024, 10, 6, 2, 15)),
+        Utc.with_ymd_and_hms(2024, 10, 5, 15, 45, 0).unwrap()
+    );
+}
+
+#[test]
+fn test_resolve_repeated_time() {
+    // London falls back from 02:00 to 01:00 on 2024-10-27, 01:30 happens twice
+    let london = parse_tz("Europe/London").unwrap();
+    assert_eq!(
+        resolve_local(&london, local(2024, 10, 27, 1, 30)),
+        Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap()
+    );
+    assert_eq!(
+        resolve_local(&london, local(2024, 10, 28, 1, 30)),
+        Utc.with_ymd_and_hms(2024, 10, 28, 1, 30, 0).unwrap()
+    );
+}
+
+#[test]
+fn test_next_wall_time_across_dst() {
+    let new_york = parse_tz("America/New_York").unwrap();
+    let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+
+    // set the evening before the clocks change, it still goes off at 09:00
+    let after = Utc.with_ymd_and_hms(2024, 3, 10, 1, 0, 0).unwrap();
+    assert_eq!(
+        next_wall_time(&new_york, nine, after),
+        Utc.with_ymd_and_hms(2024, 3, 10, 13, 0, 0).unwrap()
+    );
+
+    // already past 09:00 today, so tomorrow
+    let after = Utc.with_ymd_and_hms(2024, 3, 10, 14, 0, 0).unwrap();
+    assert_eq!(
+        next_wall_time(&new_york, nine, after),
+        Utc.with_ymd_and_hms(2024, 3, 11, 13, 0, 0).unwrap()
+    );
+
+    // a time that is skipped that day still goes off, just after the gap
+    let half_two = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+    let after = Utc.with_ymd_and_hms(2024, 3, 10, 5, 0, 0).unwrap();
+    assert_eq!(
+        next_wall_time(&new_york, half_two, after),
+        Utc.with_ymd_and_hms(2024, 3, 10, 7, 30, 0).unwrap()
+    );
+}
+
+#[test]
+fn test_format_wall_time() {
+    let tokyo = parse_tz("Asia/Tokyo").unwrap();
+    let now = Utc.with_ymd_and_hms(2024, 10, 7, 0, 0, 0).unwrap();
+
+    let today = Utc.with_ymd_and_hms(2024, 10, 7, 2, 0, 0).unwrap();
+    assert_eq!(format_wall_time(&today, &now, &tokyo), "11:00");
+    let tomorrow = Utc.with_ymd_and_hms(2024, 10, 8, 2, 0, 0).unwrap();
+    assert_eq!(format_wall_time(&tomorrow, &now, &tokyo), "Tue 11:00");
+    let later = Utc.with_ymd_and_hms(2024, 12, 24, 2, 0, 0).unwrap();
+    assert_eq!(format_wall_time(&later, &now, &tokyo), "2024-12-24 11:00");
+}
+
+#[test]
+fn test_nag_tz() {
+    let mut nag = Nag {
+        tz: Some("Asia/Tokyo".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(nag_tz(&nag), Some(parse_tz("Asia/Tokyo").unwrap()));
+
+    nag.tz = Some("Mars/Olympus_Mons".to_string());
+    assert_eq!(nag_tz(&nag), None);
+    assert!(parse_tz("Mars/Olympus_Mons").is_err());
+}
@@ -0,0 +1,413 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::complete;
+
+use clap_complete::engine::ArgValueCandidates;
+use common::{Anchor, Priority, HISTORY_LIMIT};
+
+// command line ///////////////////////////////////////////////////////////////
+
+/// The whole of nag's command line, shared by main, completions and man pages.
+pub fn cli() -> clap::Command {
+    clap::Command::new("nag")
+        .about("Manage nag messages")
+        // `nag tea` adds a nag from the tea preset
+        .allow_external_subcommands(true)
+        .external_subcommand_value_parser(clap::value_parser!(String))
+        .arg(
+            clap::Arg::new("remote")
+                .long("remote")
+                .global(true)
+                .help("Talk to a nagd listening on host:port instead of the local socket"),
+        )
+        .arg(
+            clap::Arg::new("tls")
+                .long("tls")
+                .global(true)
+                .action(clap::ArgAction::SetTrue)
+                .help("Use TLS for --remote, verified against remote.tls_ca"),
+        )
+        .subcommand(filter_args(
+            clap::Command::new("list").about("List all active nags"),
+        ))
+        .subcommand(
+            clap::Command::new("add")
+                .about("Adds a new nag")
+                .arg(
+                    clap::Arg::new("duration")
+                        .required_unless_present("preset")
+                        .help("Duration eg: \"1h\" \"2d5h6m3s\", from the anchor with --depends-on, or a time of day eg: \"09:00\""),
+                )
+                .arg(
+                    clap::Arg::new("name")
+                        .required_unless_present("preset")
+                        .help("The name for this nag"),
+                )
+                .arg(
+                    clap::Arg::new("sound_file")
+                        .required(false)
+                        .add(ArgValueCandidates::new(complete::sound_files))
+                        .help("Sound file to play, a bare name is looked up in sounds_dir"),
+                )
+                .arg(
+                    clap::Arg::new("priority")
+                        .short('p')
+                        .long("priority")
+                        .value_parser(Priority::ALL.map(|p| p.as_str()))
+                        .help("How urgently the nag is delivered, normal by default"),
+                )
+                .arg(
+                    clap::Arg::new("tag")
+                        .short('t')
+                        .long("tag")
+                        .action(clap::ArgAction::Append)
+                        .add(ArgValueCandidates::new(complete::nag_tags))
+                        .help("Tag this nag (may be repeated)"),
+                )
+                .arg(
+                    clap::Arg::new("depends_on")
+                        .long("depends-on")
+                        .value_parser(clap::value_parser!(u64))
+                        .add(ArgValueCandidates::new(complete::nag_ids))
+                        .help("Time the nag from the nag with this id instead of from now"),
+                )
+                .arg(
+                    clap::Arg::new("anchor")
+                        .long("anchor")
+                        .value_parser(Anchor::ALL.map(|anchor| anchor.as_str()))
+                        .default_value("fired")
+                        .requires("depends_on")
+                        .help("Whether the duration counts from it firing, being dismissed or back from when it fires"),
+                )
+                .arg(
+                    clap::Arg::new("tz")
+                        .long("tz")
+                        .add(ArgValueCandidates::new(complete::timezone_names))
+                        .help("IANA timezone a time of day is in eg: \"Asia/Tokyo\", the nag is also shown in it"),
+                )
+                .arg(
+                    clap::Arg::new("tool")
+                        .long("tool")
+                        .add(ArgValueCandidates::new(complete::tool_names))
+                        .help("Show the nag with this tool from [tools] in the config instead of nag_tool"),
+                )
+                .arg(
+                    clap::Arg::new("audio_tool")
+                        .long("audio-tool")
+                        .add(ArgValueCandidates::new(complete::tool_names))
+                        .help("Play the sound with this tool from [tools] in the config instead of audio_tool"),
+                )
+                .arg(
+                    clap::Arg::new("preset")
+                        .long("preset")
+                        .add(ArgValueCandidates::new(complete::preset_names))
+                        .help("Start from a preset in the config, the other arguments override it"),
+                )
+                .arg(
+                    clap::Arg::new("escalate")
+                        .long("escalate")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .help("Re-show the nag every N minutes until it is dismissed"),
+                )
+                .arg(
+                    clap::Arg::new("escalate_max")
+                        .long("escalate-max")
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value("3")
+                        .requires("escalate")
+                        .help("How many times to re-show the nag"),
+                )
+                .arg(
+                    clap::Arg::new("escalate_loop")
+                        .long("escalate-loop")
+                        .action(clap::ArgAction::SetTrue)
+                        .requires("escalate")
+                        .help("Loop the sound once the nag has been re-shown"),
+                ),
+        )
+        .subcommand(clap::Command::new("edit").about("edits all tags"))
+        .subcommand(clap::Command::new("presets").about("List the presets from the config"))
+        .subcommand(filter_args(
+            clap::Command::new("rm")
+                .about("Removes every nag matching the given ids and filters")
+                .arg(
+                    clap::Arg::new("ids")
+                        .num_args(0..)
+                        .value_parser(clap::value_parser!(u64))
+                        .add(ArgValueCandidates::new(complete::nag_ids))
+                        .help("Ids of the nags to remove"),
+                ),
+        ))
+        .subcommand(clap::Command::new("clear").about("Removes all nags"))
+        .subcommand(filter_args(
+            clap::Command::new("pause")
+                .about("Freezes the countdowns of the nags matching the given ids and filters")
+                .arg(
+                    clap::Arg::new("ids")
+                        .num_args(0..)
+                        .value_parser(clap::value_parser!(u64))
+                        .add(ArgValueCandidates::new(complete::nag_ids))
+                        .help("Ids of the nags to pause"),
+                )
+                .arg(
+                    clap::Arg::new("all")
+                        .long("all")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Pause every nag"),
+                ),
+        ))
+        .subcommand(filter_args(
+            clap::Command::new("resume")
+                .about("Starts paused countdowns again, every paused nag without ids or filters")
+                .arg(
+                    clap::Arg::new("ids")
+                        .num_args(0..)
+                        .value_parser(clap::value_parser!(u64))
+                        .add(ArgValueCandidates::new(complete::nag_ids))
+                        .help("Ids of the nags to resume"),
+                ),
+        ))
+        .subcommand(
+            clap::Command::new("alerts").about("List nags that fired and are not dismissed"),
+        )
+        .subcommand(filter_args(
+            clap::Command::new("export")
+                .about("Writes the matching nags to stdout or a file")
+                .arg(
+                    clap::Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["ics", "csv"])
+                        .default_value("ics"),
+                )
+                .arg(
+                    clap::Arg::new("component")
+                        .long("component")
+                        .value_parser(["event", "todo"])
+                        .default_value("event")
+                        .help("Export nags as VEVENTs or VTODOs"),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("File to write instead of stdout"),
+                ),
+        ))
+        .subcommand(
+            clap::Command::new("import")
+                .about("Adds a nag for every upcoming alarm in an iCalendar file")
+                .arg(clap::Arg::new("file").required(true).help("The .ics file"))
+                .arg(
+                    clap::Arg::new("horizon")
+                        .long("horizon")
+                        .default_value("7d")
+                        .help("How far ahead to import events and recurrences"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("history")
+                .about("Show what happened to past nags")
+                .arg(clap::Arg::new("since").long("since").help(
+                    "Only events after this date, time or duration ago eg: \"2024-10-01\" \"2d\"",
+                ))
+                .arg(
+                    clap::Arg::new("until")
+                        .long("until")
+                        .help("Only events up to this date, time or duration ago"),
+                )
+                .arg(
+                    clap::Arg::new("name")
+                        .short('n')
+                        .long("name")
+                        .help("Only nags whose name contains this text"),
+                )
+                .arg(
+                    clap::Arg::new("limit")
+                        .long("limit")
+                        .value_parser(clap::value_parser!(usize))
+                        .help(format!(
+                            "Only the latest this many events, {} without --since",
+                            HISTORY_LIMIT
+                        )),
+                )
+                .arg(
+                    clap::Arg::new("all")
+                        .long("all")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("limit")
+                        .help("Every event, as many as fit in a reply from nagd"),
+                )
+                .arg(
+                    clap::Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print the events as JSON"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("run")
+                .about("Run a command and nag once it exits")
+                .arg(
+                    clap::Arg::new("name")
+                        .short('n')
+                        .long("name")
+                        .help("What to call the command in the nag, the command line by default"),
+                )
+                .arg(
+                    clap::Arg::new("timeout").long("timeout").help(
+                        "Also nag if the command is still running after this long eg: \"30m\"",
+                    ),
+                )
+                .arg(
+                    clap::Arg::new("priority")
+                        .short('p')
+                        .long("priority")
+                        .value_parser(Priority::ALL.map(|p| p.as_str()))
+                        .default_value("normal")
+                        .help("How urgently the nags are delivered"),
+                )
+                .arg(
+                    clap::Arg::new("tag")
+                        .short('t')
+                        .long("tag")
+                        .action(clap::ArgAction::Append)
+                        .add(ArgValueCandidates::new(complete::nag_tags))
+                        .help("Tag the nags (may be repeated)"),
+                )
+                .arg(
+                    clap::Arg::new("command")
+                        .required(true)
+                        .num_args(1..)
+                        .last(true)
+                        .help("The command to run, after --"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("wait")
+                .about("Block until a nag fires, is dismissed or is removed")
+                .after_help(
+                    "Exits 0 when the nag fired, 3 when it was dismissed, 4 when it was removed, \
+                     5 when nagd went away and 6 on --timeout",
+                )
+                .arg(
+                    clap::Arg::new("nag")
+                        .required(true)
+                        .add(ArgValueCandidates::new(complete::nag_ids))
+                        .help("Id of the nag, or text only its name contains"),
+                )
+                .arg(
+                    clap::Arg::new("timeout")
+                        .long("timeout")
+                        .help("Give up after this long eg: \"30m\""),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("routine")
+                .about("Run chains of nags from the config, pomodoro is built in")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("start")
+                        .about("Start a routine, or start it over")
+                        .arg(routine_arg()),
+                )
+                .subcommand(
+                    clap::Command::new("status").about("Show where running routines are at"),
+                )
+                .subcommand(
+                    clap::Command::new("skip")
+                        .about("Move a routine on to its next step now")
+                        .arg(routine_arg()),
+                )
+                .subcommand(
+                    clap::Command::new("stop")
+                        .about("Stop a routine and remove its pending nag")
+                        .arg(routine_arg()),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("dnd")
+                .about("Do not disturb, nags are quiet like during the config's quiet hours")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("on").about("Turn do not disturb on").arg(
+                        clap::Arg::new("for")
+                            .long("for")
+                            .help("Turn it off again after this long eg: \"1h\""),
+                    ),
+                )
+                .subcommand(clap::Command::new("off").about("Turn do not disturb off"))
+                .subcommand(
+                    clap::Command::new("status").about("Show do not disturb and quiet hours"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("tui").about("Browse and manage nags in a full screen interface"),
+        )
+        .subcommand(
+            clap::Command::new("completions")
+                .about("Print the script that hooks nag's completions into a shell")
+                .after_help("eg: `nag completions bash >> ~/.bashrc`")
+                .arg(
+                    clap::Arg::new("shell")
+                        .required(true)
+                        .value_parser(complete::SHELLS),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("man")
+                .about("Generate man pages for nag and its subcommands")
+                .arg(clap::Arg::new("output").short('o').long("output").help(
+                    "Directory to write a page per subcommand to, instead of nag.1 to stdout",
+                )),
+        )
+}
+
+// ----------------------------------------------------------------------------
+
+fn filter_args(command: clap::Command) -> clap::Command {
+    command
+        .arg(
+            clap::Arg::new("tag")
+                .short('t')
+                .long("tag")
+                .action(clap::ArgAction::Append)
+                .add(ArgValueCandidates::new(complete::nag_tags))
+                .help("Only nags with this tag (may be repeated)"),
+        )
+        .arg(
+            clap::Arg::new("name")
+                .short('n')
+                .long("name")
+                .add(ArgValueCandidates::new(complete::nag_names))
+                .help("Only nags whose name contains this text"),
+        )
+        .arg(
+            clap::Arg::new("regex")
+                .short('r')
+                .long("regex")
+                .help("Only nags whose name matches this regex"),
+        )
+        .arg(
+            clap::Arg::new("after")
+                .long("after")
+                .help("Only nags firing later than this duration from now"),
+        )
+        .arg(
+            clap::Arg::new("before")
+                .long("before")
+                .help("Only nags firing within this duration from now"),
+        )
+}
+
+// ----------------------------------------------------------------------------
+
+fn routine_arg() -> clap::Arg {
+    clap::Arg::new("routine")
+        .required(true)
+        .add(ArgValueCandidates::new(complete::routine_names))
+        .help("Name of the routine")
+}
@@ -0,0 +1,147 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::cli::cli;
+
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use common::blocking::Client;
+use common::{expand_path, Nag, NagFilter, CONFIG};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// shell registration /////////////////////////////////////////////////////////
+
+pub const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "elvish"];
+
+// the variable that makes nag answer completion requests instead of running
+const COMPLETE_VAR: &str = "COMPLETE";
+
+// ----------------------------------------------------------------------------
+
+/// Answers the shell when nag was run for completions, and exits.
+pub fn complete_if_asked() {
+    clap_complete::CompleteEnv::with_factory(cli)
+        .var(COMPLETE_VAR)
+        .complete();
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn print_completions(shell: &str) -> io::Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells.completer(shell).expect("shell is one of SHELLS");
+    completer.write_registration(COMPLETE_VAR, "nag", "nag", "nag", &mut io::stdout())
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn write_man_pages(output: Option<&String>) -> io::Result<()> {
+    match output {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(cli(), dir)
+        }
+        None => clap_mangen::Man::new(cli()).render(&mut io::stdout()),
+    }
+}
+
+// dynamic values /////////////////////////////////////////////////////////////
+
+// a tab press shouldn't hang on a stuck daemon
+const DAEMON_TIMEOUT: Duration = Duration::from_millis(300);
+
+fn daemon_nags() -> Vec<Nag> {
+    Client::local()
+        .with_timeout(DAEMON_TIMEOUT)
+        .list(NagFilter::default())
+        .unwrap_or_default()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn nag_ids() -> Vec<CompletionCandidate> {
+    daemon_nags()
+        .into_iter()
+        .map(|nag| CompletionCandidate::new(nag.id.to_string()).help(Some(nag.name.into())))
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn nag_names() -> Vec<CompletionCandidate> {
+    let names: BTreeSet<String> = daemon_nags().into_iter().map(|nag| nag.name).collect();
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn nag_tags() -> Vec<CompletionCandidate> {
+    let tags: BTreeSet<String> = daemon_nags().into_iter().flat_map(|nag| nag.tags).collect();
+    tags.into_iter().map(CompletionCandidate::new).collect()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn preset_names() -> Vec<CompletionCandidate> {
+    CONFIG
+        .presets
+        .iter()
+        .map(|(name, preset)| {
+            CompletionCandidate::new(name).help(preset.name.clone().map(Into::into))
+        })
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn tool_names() -> Vec<CompletionCandidate> {
+    CONFIG
+        .tools
+        .iter()
+        .map(|(name, command)| CompletionCandidate::new(name).help(Some(command.join(" ").into())))
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn routine_names() -> Vec<CompletionCandidate> {
+    CONFIG
+        .routine_names()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn timezone_names() -> Vec<CompletionCandidate> {
+    chrono_tz::TZ_VARIANTS
+        .iter()
+        .map(|tz| CompletionCandidate::new(tz.name()))
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+
+/// Files in the configured `sounds_dir`, by the bare names `nag add` resolves.
+pub fn sound_files() -> Vec<CompletionCandidate> {
+    let Some(Ok(sounds_dir)) = CONFIG.sounds_dir.as_deref().map(expand_path) else {
+        return Vec::new();
+    };
+    let Ok(entries) = PathBuf::from(sounds_dir).read_dir() else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    files.into_iter().map(CompletionCandidate::new).collect()
+}
@@ -3,179 +3,896 @@
 // SPDX-License-Identifier: MIT
 //
 
-use chrono::Utc;
+mod cli;
+mod complete;
+mod tui;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Offset, Utc};
 use common::{
-    read_nags_from_file, recv_response, send_command, write_nags_to_file, Command, Nag, Response,
-    COMSOCK_PATH, CONFIG,
+    format_duration, format_wall_time, nag_tz, nags_from_ics, nags_to_ics, next_wall_time,
+    parse_tz, read_nags_from_file, write_nags_to_file, Client, ClientError, Dependency, Endpoint,
+    ErrorCode, Escalation, EventKind, HistoryFilter, IcsComponent, Nag, NagFilter, Priority,
+    CONFIG,
 };
 use log::info;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::{Cursor, IsTerminal};
 use std::process::Command as Proc;
 use tempfile::NamedTempFile;
-use tokio::net::UnixStream;
+
+// connection /////////////////////////////////////////////////////////////////
+
+/// The local socket unless `--remote` was given.
+fn client_from_matches(matches: &clap::ArgMatches) -> Client {
+    let Some(addr) = matches.get_one::<String>("remote") else {
+        return Client::local();
+    };
+
+    // never a flag, arguments show up in ps
+    let Some(token) = std::env::var("NAG_TOKEN")
+        .ok()
+        .or_else(|| CONFIG.remote.token.clone())
+    else {
+        eprintln!("--remote needs NAG_TOKEN or remote.token in the config");
+        std::process::exit(1);
+    };
+
+    let tls_ca = if matches.get_flag("tls") {
+        let Some(tls_ca) = &CONFIG.remote.tls_ca else {
+            eprintln!("--tls needs remote.tls_ca in the config");
+            std::process::exit(1);
+        };
+        Some(tls_ca.clone())
+    } else {
+        None
+    };
+
+    Client::new(Endpoint::Remote {
+        addr: addr.clone(),
+        token,
+        tls_ca,
+    })
+}
+
+// ----------------------------------------------------------------------------
+
+/// Hands nagd this session's display and audio variables, so the tools it
+/// starts reach this session even when nagd was started before it.
+async fn send_session_env(client: &mut Client) {
+    // a session without a display, eg: over ssh or from cron, has nothing
+    // worth handing over and shouldn't touch a good one
+    let has_display = ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()));
+    let env = CONFIG.filter_session_env(std::env::vars());
+    if !has_display || env.is_empty() {
+        return;
+    }
+
+    // the command itself reports nagd being unreachable
+    if let Err(err) = client.set_session_env(env).await {
+        info!("Failed to send the session environment ({})", err);
+    }
+}
+
+// filter arguments ///////////////////////////////////////////////////////////
+
+fn filter_from_matches(matches: &clap::ArgMatches) -> NagFilter {
+    let from_now = |arg: &str| {
+        matches
+            .get_one::<String>(arg)
+            .map(|duration| match duration_str::parse(duration) {
+                Ok(duration_parsed) => Utc::now() + duration_parsed,
+                Err(err) => panic!("Failed to parse duration {} ({:?})", duration, err),
+            })
+    };
+
+    NagFilter {
+        ids: matches
+            .try_get_many::<u64>("ids")
+            .ok()
+            .flatten()
+            .map(|ids| ids.copied().collect())
+            .unwrap_or_default(),
+        tags: matches
+            .get_many::<String>("tag")
+            .map(|tags| tags.cloned().collect())
+            .unwrap_or_default(),
+        name: matches.get_one::<String>("name").cloned(),
+        name_regex: matches.get_one::<String>("regex").cloned(),
+        after: from_now("after"),
+        before: from_now("before"),
+    }
+}
 
 // entry point ////////////////////////////////////////////////////////////////
 
 #[tokio::main]
 async fn main() {
+    complete::complete_if_asked();
     env_logger::init();
 
-    let matches = clap::Command::new("nag")
-        .about("Manage nag messages")
-        .subcommand(clap::Command::new("list").about("List all active nags"))
-        .subcommand(
-            clap::Command::new("add")
-                .about("Adds a new nag")
-                .arg(
-                    clap::Arg::new("duration")
-                        .required(true)
-                        .help("Duration eg: \"1h\" \"2d5h6m3s\""),
-                )
-                .arg(
-                    clap::Arg::new("name")
-                        .required(true)
-                        .help("The name for this nag"),
-                )
-                .arg(
-                    clap::Arg::new("sound_file")
-                        .required(false)
-                        .help("Path to a sound file to play"),
-                ),
-        )
-        .subcommand(clap::Command::new("edit").about("edits all tags"))
-        .get_matches();
+    let matches = expand_preset(cli::cli().get_matches());
 
-    if matches.subcommand_matches("list").is_some() {
-        list_nags().await;
+    // none of these need the daemon
+    if let Some(completions_matches) = matches.subcommand_matches("completions") {
+        let shell = completions_matches.get_one::<String>("shell").unwrap();
+        complete::print_completions(shell).expect("Failed to write completions");
+        return;
+    } else if let Some(man_matches) = matches.subcommand_matches("man") {
+        complete::write_man_pages(man_matches.get_one::<String>("output"))
+            .expect("Failed to write man pages");
+        return;
+    } else if matches.subcommand_matches("presets").is_some() {
+        list_presets();
+        return;
+    }
+
+    let mut client = client_from_matches(&matches);
+    if !matches.contains_id("remote") {
+        send_session_env(&mut client).await;
+    }
+
+    let result = if let Some(list_matches) = matches.subcommand_matches("list") {
+        list_nags(&mut client, filter_from_matches(list_matches)).await
     } else if let Some(add_matches) = matches.subcommand_matches("add") {
-        let duration = add_matches.get_one::<String>("duration").unwrap();
-        let name = add_matches.get_one::<String>("name").unwrap();
-        let sound_file = add_matches.get_one::<String>("sound_file");
-        add_nag(duration, name, sound_file).await;
+        add_nag(&mut client, nag_from_matches(add_matches)).await
     } else if matches.subcommand_matches("edit").is_some() {
-        edit_nags().await;
+        edit_nags(&mut client).await
+    } else if let Some(rm_matches) = matches.subcommand_matches("rm") {
+        let filter = filter_from_matches(rm_matches);
+        remove_nags(&mut client, Some(filter)).await
+    } else if matches.subcommand_matches("clear").is_some() {
+        remove_nags(&mut client, None).await
+    } else if let Some(pause_matches) = matches.subcommand_matches("pause") {
+        let filter = filter_from_matches(pause_matches);
+        if filter.is_empty() && !pause_matches.get_flag("all") {
+            eprintln!("Give the nags to pause, or --all");
+            std::process::exit(1);
+        }
+        pause_nags(&mut client, filter).await
+    } else if let Some(resume_matches) = matches.subcommand_matches("resume") {
+        resume_nags(&mut client, filter_from_matches(resume_matches)).await
+    } else if matches.subcommand_matches("alerts").is_some() {
+        list_alerts(&mut client).await
+    } else if let Some(export_matches) = matches.subcommand_matches("export") {
+        export_nags(&mut client, export_matches).await
+    } else if let Some(import_matches) = matches.subcommand_matches("import") {
+        let file = import_matches.get_one::<String>("file").unwrap();
+        let horizon = import_matches.get_one::<String>("horizon").unwrap();
+        import_nags(&mut client, file, horizon).await
+    } else if let Some(run_matches) = matches.subcommand_matches("run") {
+        run_command(&mut client, run_matches).await
+    } else if let Some(wait_matches) = matches.subcommand_matches("wait") {
+        let target = wait_matches.get_one::<String>("nag").unwrap();
+        let timeout =
+            wait_matches
+                .get_one::<String>("timeout")
+                .map(|timeout| match duration_str::parse_std(timeout) {
+                    Ok(timeout) => timeout,
+                    Err(err) => panic!("Failed to parse duration {} ({:?})", timeout, err),
+                });
+        wait_nag(&mut client, target, timeout).await
+    } else if let Some(routine_matches) = matches.subcommand_matches("routine") {
+        routine(&mut client, routine_matches).await
+    } else if let Some(dnd_matches) = matches.subcommand_matches("dnd") {
+        dnd(&mut client, dnd_matches).await
+    } else if matches.subcommand_matches("tui").is_some() {
+        tui::run(&mut client).await
+    } else if let Some(history_matches) = matches.subcommand_matches("history") {
+        let filter = HistoryFilter {
+            since: history_matches
+                .get_one::<String>("since")
+                .map(|since| parse_time_arg(since, false)),
+            until: history_matches
+                .get_one::<String>("until")
+                .map(|until| parse_time_arg(until, true)),
+            name: history_matches.get_one::<String>("name").cloned(),
+            limit: if history_matches.get_flag("all") {
+                Some(usize::MAX)
+            } else {
+                history_matches.get_one::<usize>("limit").copied()
+            },
+        };
+        show_history(&mut client, filter, history_matches.get_flag("json")).await
+    } else {
+        Ok(())
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error! {}", err);
+        std::process::exit(1);
     }
 }
 
 // ----------------------------------------------------------------------------
 
-async fn fetch_nags() -> Result<Vec<Nag>, Box<(dyn std::error::Error + Sync + Send)>> {
-    info!("Connecting to socket...");
-    let stream = UnixStream::connect(COMSOCK_PATH)
-        .await
-        .expect("Failed to connect");
-    let (mut read_stream, mut write_stream) = stream.into_split();
+/// `nag tea 10m` is short for `nag add --preset tea 10m`.
+fn expand_preset(matches: clap::ArgMatches) -> clap::ArgMatches {
+    let Some((preset, preset_matches)) = matches.subcommand() else {
+        return matches;
+    };
+    if cli::cli().find_subcommand(preset).is_some() {
+        return matches;
+    }
+    if !CONFIG.presets.contains_key(preset) {
+        eprintln!("error: unrecognized subcommand or preset '{}'", preset);
+        std::process::exit(2);
+    }
 
-    info!("Connected, sending list_nags command");
-    send_command(&mut write_stream, Command::ListNags)
-        .await
-        .expect("Failed to send command");
+    // the global options clap already took go in front of `add`
+    let mut args = vec![cli::cli().get_name().to_string()];
+    for arg in cli::cli().get_arguments() {
+        let id = arg.get_id().as_str();
+        if matches.value_source(id) != Some(clap::parser::ValueSource::CommandLine) {
+            continue;
+        }
+        let flag = format!("--{}", arg.get_long().expect("global options are long"));
+        match arg.get_action() {
+            clap::ArgAction::SetTrue => args.push(flag),
+            _ => {
+                for value in matches.get_many::<String>(id).into_iter().flatten() {
+                    args.extend([flag.clone(), value.clone()]);
+                }
+            }
+        }
+    }
 
-    info!("Command sent, waiting reponse...");
-    match recv_response(&mut read_stream)
-        .await
-        .expect("Failed to recv response")
-    {
-        Response::Ok => Ok(vec![]),
-        Response::NagList { nags } => Ok(nags),
-        Response::Error { code, msg } => {
-            let error_message = format!(
-                "Error! {} ({:?})",
-                msg.unwrap_or("No Message".to_string()),
-                code
-            );
-            Err(Box::<dyn std::error::Error + Sync + Send>::from(
-                error_message,
-            ))
+    // everything after the preset's name was taken as its arguments
+    let preset_args: Vec<String> = preset_matches
+        .get_many::<String>("")
+        .map(|args| args.cloned().collect())
+        .unwrap_or_default();
+    args.extend([
+        "add".to_string(),
+        "--preset".to_string(),
+        preset.to_string(),
+    ]);
+    args.extend(preset_args);
+
+    cli::cli().get_matches_from(args)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Accepts an RFC 3339 time, a local date or a duration back from now, dates
+/// given as an upper bound include the whole day.
+fn parse_time_arg(arg: &str, end_of_day: bool) -> DateTime<Utc> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(arg) {
+        return time.with_timezone(&Utc);
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(arg, "%Y-%m-%d") {
+        let date = if end_of_day {
+            date.succ_opt().unwrap_or(date)
+        } else {
+            date
+        };
+        return date
+            .and_time(NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .expect("local midnight does not exist")
+            .with_timezone(&Utc);
+    }
+
+    match duration_str::parse(arg) {
+        Ok(duration_parsed) => Utc::now() - duration_parsed,
+        Err(err) => panic!("Failed to parse time {} ({:?})", arg, err),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn nag_from_matches(add_matches: &clap::ArgMatches) -> Nag {
+    let preset = match add_matches.get_one::<String>("preset") {
+        Some(name) => match CONFIG.presets.get(name) {
+            Some(preset) => Some((name, preset)),
+            None => {
+                eprintln!("No preset called {}, see nag presets", name);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let Some(duration) = add_matches
+        .get_one::<String>("duration")
+        .or(preset.and_then(|(_, preset)| preset.duration.as_ref()))
+    else {
+        eprintln!("The preset has no duration, give one");
+        std::process::exit(1);
+    };
+    let tz = add_matches
+        .get_one::<String>("tz")
+        .map(|name| match parse_tz(name) {
+            Ok(tz) => tz,
+            Err(err) => {
+                eprintln!("{}, eg: Europe/London", err);
+                std::process::exit(1);
+            }
+        });
+
+    // a time of day is the next time the clock in --tz, or here, shows it
+    let (end_time, duration_parsed) = match NaiveTime::parse_from_str(duration, "%H:%M") {
+        Ok(time) => {
+            if add_matches.contains_id("depends_on") {
+                eprintln!("--depends-on needs a duration, not a time of day");
+                std::process::exit(1);
+            }
+            let end_time = match tz {
+                Some(tz) => next_wall_time(&tz, time, Utc::now()),
+                None => next_wall_time(&Local, time, Utc::now()),
+            };
+            (
+                end_time,
+                (end_time - Utc::now()).to_std().unwrap_or_default(),
+            )
+        }
+        Err(_) => match duration_str::parse(duration) {
+            Ok(duration_parsed) => (Utc::now() + duration_parsed, duration_parsed),
+            Err(err) => panic!("Failed to parse duration {} ({:?})", duration, err),
+        },
+    };
+
+    let escalation = add_matches
+        .get_one::<u64>("escalate")
+        .map(|interval_minutes| Escalation {
+            interval_minutes: *interval_minutes,
+            max_count: *add_matches.get_one::<u32>("escalate_max").unwrap(),
+            loop_sound: add_matches.get_flag("escalate_loop"),
+            sound_file: None,
+        });
+
+    let mut nag = match preset {
+        Some((preset_name, preset)) => Nag {
+            name: preset.name.clone().unwrap_or_else(|| preset_name.clone()),
+            sound_file: preset.sound_file.clone(),
+            priority: preset.priority.unwrap_or_default(),
+            tags: preset.tags.clone(),
+            escalation: preset.escalation.clone(),
+            nag_tool: preset.nag_tool.clone(),
+            audio_tool: preset.audio_tool.clone(),
+            ..Default::default()
+        },
+        None => Nag::default(),
+    };
+
+    nag.end_time = end_time;
+    nag.tz = tz.map(|tz| tz.name().to_string());
+    if let Some(name) = add_matches.get_one::<String>("name") {
+        nag.name = name.clone();
+    }
+    if let Some(sound_file) = add_matches.get_one::<String>("sound_file") {
+        nag.sound_file = Some(sound_file.clone());
+    }
+    if let Some(priority) = add_matches.get_one::<String>("priority") {
+        nag.priority = priority.parse().expect("invalid priority");
+    }
+    if let Some(tags) = add_matches.get_many::<String>("tag") {
+        nag.tags = tags.cloned().collect();
+    }
+    if escalation.is_some() {
+        nag.escalation = escalation;
+    }
+    if let Some(tool) = add_matches.get_one::<String>("tool") {
+        nag.nag_tool = Some(tool.clone());
+    }
+    if let Some(tool) = add_matches.get_one::<String>("audio_tool") {
+        nag.audio_tool = Some(tool.clone());
+    }
+    if let Err(err) = check_sound_files(&mut nag) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    // nagd works out end_time from the parent
+    if let Some(parent) = add_matches.get_one::<u64>("depends_on") {
+        nag.depends_on = Some(Dependency {
+            parent: *parent,
+            anchor: add_matches
+                .get_one::<String>("anchor")
+                .unwrap()
+                .parse()
+                .expect("invalid anchor"),
+            offset_seconds: duration_parsed.as_secs(),
+        });
+    }
+    nag
+}
+
+// ----------------------------------------------------------------------------
+
+/// Resolves the nag's sound files to paths nagd can play, warning about files
+/// that don't look like audio.
+fn check_sound_files(nag: &mut Nag) -> Result<(), String> {
+    let escalation_sound = nag
+        .escalation
+        .as_mut()
+        .and_then(|escalation| escalation.sound_file.as_mut());
+
+    for sound_file in nag.sound_file.iter_mut().chain(escalation_sound) {
+        let sound = CONFIG.resolve_sound_file(sound_file)?;
+        if sound.format.is_none() {
+            eprintln!("Warning: {} doesn't look like an audio file", sound.path);
+        }
+        *sound_file = sound.path;
+    }
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+fn list_presets() {
+    for (preset_name, preset) in &CONFIG.presets {
+        let mut line = format!(
+            "{:<12} {:>8} {:<8}  {}",
+            preset_name,
+            preset.duration.as_deref().unwrap_or("-"),
+            preset.priority.unwrap_or_default(),
+            preset.name.as_deref().unwrap_or(preset_name)
+        );
+        if !preset.tags.is_empty() {
+            line.push_str(&format!("  [{}]", preset.tags.join(", ")));
+        }
+        if let Some(sound_file) = &preset.sound_file {
+            line.push_str(&format!("  {}", sound_file));
+        }
+        println!("{}", line);
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn priority_colour(priority: Priority) -> &'static str {
+    match priority {
+        Priority::Low => "\x1b[2m",
+        Priority::Normal => "",
+        Priority::High => "\x1b[33m",
+        Priority::Critical => "\x1b[1;31m",
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn list_nags(client: &mut Client, filter: NagFilter) -> Result<(), ClientError> {
+    let mut nags = client.list(filter).await?;
+    nags.sort_by_key(|nag| (Reverse(nag.priority), nag.end_time));
+    let quiet = client.quiet().await?;
+    let now = Utc::now();
+
+    let colour = std::io::stdout().is_terminal();
+    for nag in nags {
+        let mut line = format!(
+            "{:>4} {:<8} {:>12} {:>16}  {}",
+            nag.id,
+            nag.priority,
+            nag.remaining(),
+            if nag.is_paused() {
+                "paused".to_string()
+            } else {
+                format_wall_time(&nag.end_time, &now, &Local)
+            },
+            nag.name
+        );
+        if !nag.tags.is_empty() {
+            line.push_str(&format!("  [{}]", nag.tags.join(", ")));
+        }
+        if let Some(depends_on) = &nag.depends_on {
+            line.push_str(&format!("  ({})", depends_on));
+        }
+        // only worth showing when its clock differs from this one
+        if let Some(tz) = nag_tz(&nag).filter(|_| !nag.is_paused()) {
+            let offset = nag.end_time.with_timezone(&tz).offset().fix();
+            if offset != nag.end_time.with_timezone(&Local).offset().fix() {
+                let wall_time = format_wall_time(&nag.end_time, &now, &tz);
+                line.push_str(&format!("  ({} {})", wall_time, tz.name()));
+            }
+        }
+        // nagd works it out, quiet hours go by its clock
+        if let Some(effect) = quiet.effects.get(&nag.id) {
+            line.push_str(&format!("  ({} by quiet)", effect));
+        }
+        if colour {
+            println!("{}{}\x1b[0m", priority_colour(nag.priority), line);
+        } else {
+            println!("{}", line);
         }
     }
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+async fn add_nag(client: &mut Client, nag: Nag) -> Result<(), ClientError> {
+    client.add(nag).await?;
+    println!("Success");
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+/// Removes the nags matching `filter`, or all of them without one.
+async fn remove_nags(client: &mut Client, filter: Option<NagFilter>) -> Result<(), ClientError> {
+    let removed = match filter {
+        Some(filter) => client.remove(filter).await?,
+        None => client.clear().await?,
+    };
+    println!("Removed {} nag(s)", removed.len());
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+async fn pause_nags(client: &mut Client, filter: NagFilter) -> Result<(), ClientError> {
+    let paused = client.pause(filter).await?;
+    println!("Paused {} nag(s)", paused.len());
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+async fn resume_nags(client: &mut Client, filter: NagFilter) -> Result<(), ClientError> {
+    let resumed = client.resume(filter).await?;
+    println!("Resumed {} nag(s)", resumed.len());
+    Ok(())
 }
 
 // ----------------------------------------------------------------------------
 
-async fn list_nags() {
-    match fetch_nags().await {
-        Ok(nags) => {
-            println!("{:?}", nags);
+async fn list_alerts(client: &mut Client) -> Result<(), ClientError> {
+    for alert in client.alerts().await? {
+        println!(
+            "{:>4} {:<8} fired {}  escalated {}x  {}",
+            alert.nag.id,
+            alert.nag.priority,
+            alert.fired_at.with_timezone(&Local).format("%H:%M:%S"),
+            alert.escalations,
+            alert.nag.name
+        );
+    }
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+async fn export_nags(
+    client: &mut Client,
+    export_matches: &clap::ArgMatches,
+) -> Result<(), ClientError> {
+    let nags = client.list(filter_from_matches(export_matches)).await?;
+
+    let output = match export_matches.get_one::<String>("format").unwrap().as_str() {
+        "csv" => {
+            let mut cursor = Cursor::new(Vec::new());
+            write_nags_to_file(&nags, &mut cursor).expect("Failed to write nags");
+            String::from_utf8(cursor.into_inner()).expect("nags are not utf8")
         }
+        _ => {
+            let component = match export_matches
+                .get_one::<String>("component")
+                .unwrap()
+                .as_str()
+            {
+                "todo" => IcsComponent::Todo,
+                _ => IcsComponent::Event,
+            };
+            nags_to_ics(&nags, component)
+        }
+    };
+
+    match export_matches.get_one::<String>("output") {
+        Some(path) => std::fs::write(path, output).expect("Failed to write export"),
+        None => print!("{}", output),
+    }
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+async fn import_nags(client: &mut Client, file: &str, horizon: &str) -> Result<(), ClientError> {
+    let ics = std::fs::read_to_string(file).expect("Failed to read calendar file");
+    let horizon = match duration_str::parse(horizon) {
+        Ok(horizon) => horizon,
+        Err(err) => panic!("Failed to parse duration {} ({:?})", horizon, err),
+    };
+
+    let now = Utc::now();
+    let nags = match nags_from_ics(&ics, now, now + horizon) {
+        Ok(nags) => nags,
         Err(err) => {
-            println!("{}", err);
+            eprintln!("Failed to import {}: {}", file, err);
+            return Ok(());
         }
+    };
+
+    let count = nags.len();
+    for nag in nags {
+        info!("Importing nag {:?}", nag);
+        client.add(nag).await?;
+    }
+
+    println!("Imported {} nag(s)", count);
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+async fn show_history(
+    client: &mut Client,
+    filter: HistoryFilter,
+    json: bool,
+) -> Result<(), ClientError> {
+    let events = client.history(filter).await?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&events).expect("Failed to encode history")
+        );
+        return Ok(());
+    }
+
+    for event in events {
+        println!(
+            "{}  {:<9} {:>4}  {}",
+            event.time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            event.kind,
+            event.nag.id,
+            event.nag.name
+        );
     }
+
+    Ok(())
 }
 
 // ----------------------------------------------------------------------------
 
-async fn add_nag(duration: &String, name: &str, sound_file: Option<&String>) {
-    let stream = UnixStream::connect(COMSOCK_PATH)
-        .await
-        .expect("Failed to connect");
-    let (mut read_stream, mut write_stream) = stream.into_split();
+/// Runs the command and nags right away once it exits, and once more if it is
+/// still running after `--timeout`.  Exits with the command's exit code.
+async fn run_command(
+    client: &mut Client,
+    run_matches: &clap::ArgMatches,
+) -> Result<(), ClientError> {
+    let command: Vec<&String> = run_matches.get_many::<String>("command").unwrap().collect();
+    let name = match run_matches.get_one::<String>("name") {
+        Some(name) => name.clone(),
+        None => command
+            .iter()
+            .map(|arg| arg.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    let timeout = run_matches.get_one::<String>("timeout").map(|timeout| {
+        match duration_str::parse_std(timeout) {
+            Ok(timeout) => timeout,
+            Err(err) => panic!("Failed to parse duration {} ({:?})", timeout, err),
+        }
+    });
+    let nag_for = |name: String| Nag {
+        end_time: Utc::now(),
+        name,
+        priority: run_matches
+            .get_one::<String>("priority")
+            .map(|p| p.parse().expect("invalid priority"))
+            .unwrap_or_default(),
+        tags: run_matches
+            .get_many::<String>("tag")
+            .map(|tags| tags.cloned().collect())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    // Ctrl-C reaches the command as well, stay around to nag about how it ended
+    let _interrupt = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
+        .expect("Failed to listen for interrupts");
+
+    let started = Utc::now();
+    let mut child = match tokio::process::Command::new(command[0])
+        .args(&command[1..])
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("Failed to run {} ({})", command[0], err);
+            std::process::exit(127);
+        }
+    };
 
-    let nag = match duration_str::parse(duration) {
-        Ok(duration_parsed) => Nag {
-            end_time: Utc::now() + duration_parsed,
-            name: name.to_string(),
-            sound_file: sound_file.cloned(),
+    let status = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => status,
+            Err(_) => {
+                let elapsed = format_duration(Utc::now() - started);
+                let nag = nag_for(format!("{} still running after {}", name, elapsed));
+                if let Err(err) = client.add(nag).await {
+                    eprintln!("Failed to nag ({})", err);
+                }
+                child.wait().await
+            }
         },
-        Err(err) => panic!("Failed to parse duration {} ({:?})", duration, err),
+        None => child.wait().await,
+    }
+    .expect("Failed to wait on command");
+
+    let outcome = match status.code() {
+        Some(code) => format!("exit {}", code),
+        None => "killed".to_string(),
     };
+    let elapsed = format_duration(Utc::now() - started);
+    let nag = nag_for(format!("{} finished ({}) after {}", name, outcome, elapsed));
+    if let Err(err) = client.add(nag).await {
+        eprintln!("Failed to nag ({})", err);
+    }
 
-    send_command(&mut write_stream, Command::AddNag { nag })
-        .await
-        .expect("Failed to send command to add nag");
+    std::process::exit(status.code().unwrap_or(1));
+}
 
-    info!("Command sent, waiting response...");
-    let response = recv_response(&mut read_stream)
-        .await
-        .expect("Failed to recv response");
+// ----------------------------------------------------------------------------
 
-    match response {
-        Response::Ok => println!("Success"),
-        Response::NagList { nags } => println!("Nags: {:?}", nags),
-        Response::Error { code, msg } => println!(
-            "Error! {} ({:?})",
-            msg.as_deref().unwrap_or("No Message"),
-            code
-        ),
+async fn routine(
+    client: &mut Client,
+    routine_matches: &clap::ArgMatches,
+) -> Result<(), ClientError> {
+    let (action, action_matches) = routine_matches.subcommand().unwrap();
+    if action == "status" {
+        for status in client.routines().await? {
+            let next = match &status.nag {
+                Some(nag) => format!("{} in {}", nag.name, nag.remaining()),
+                None => "waiting to be dismissed".to_string(),
+            };
+            println!(
+                "{:<12} step {}/{}  cycle {}  {}",
+                status.name,
+                status.step + 1,
+                status.steps,
+                status.cycle + 1,
+                next
+            );
+        }
+        return Ok(());
+    }
+
+    let name = action_matches.get_one::<String>("routine").unwrap();
+    match action {
+        "start" => client.start_routine(name).await?,
+        "skip" => client.skip_routine(name).await?,
+        _ => client.stop_routine(name).await?,
     }
+
+    println!("Success");
+    Ok(())
 }
 
 // ----------------------------------------------------------------------------
 
-async fn send_nags(nags: Vec<Nag>) -> Result<Vec<Nag>, Box<dyn std::error::Error + Sync + Send>> {
-    let stream = UnixStream::connect(COMSOCK_PATH)
-        .await
-        .expect("Failed to connect");
-    let (mut read_stream, mut write_stream) = stream.into_split();
+async fn dnd(client: &mut Client, dnd_matches: &clap::ArgMatches) -> Result<(), ClientError> {
+    match dnd_matches.subcommand().unwrap() {
+        ("on", on_matches) => {
+            let until =
+                on_matches.get_one::<String>("for").map(|length| {
+                    match duration_str::parse(length) {
+                        Ok(length) => Utc::now() + length,
+                        Err(err) => panic!("Failed to parse duration {} ({:?})", length, err),
+                    }
+                });
+            client.set_dnd(true, until).await?;
+        }
+        ("off", _) => client.set_dnd(false, None).await?,
+        _ => {
+            let status = client.quiet().await?;
+            match (status.dnd, status.dnd_until) {
+                (true, Some(until)) => println!(
+                    "do not disturb until {}",
+                    until.with_timezone(&Local).format("%H:%M")
+                ),
+                (true, None) => println!("do not disturb until turned off"),
+                (false, _) => println!("do not disturb off"),
+            }
+            for hours in &status.quiet.hours {
+                println!("quiet {}", hours);
+            }
+            if status.quiet_now {
+                println!("quiet now, in {} mode", status.quiet.mode);
+            }
+            return Ok(());
+        }
+    }
 
-    info!("Sending nags {:?}", nags);
-    send_command(&mut write_stream, Command::SetNags { nags })
-        .await
-        .expect("failed send set nags command");
+    println!("Success");
+    Ok(())
+}
 
-    info!("Command sent, waiting response...");
-    let response = recv_response(&mut read_stream)
-        .await
-        .expect("Failed to recv response");
+// ----------------------------------------------------------------------------
+
+// exit codes of `nag wait` besides 0 for fired, 1 is left to errors and 2 to
+// usage errors
+const WAIT_DISMISSED: i32 = 3;
+const WAIT_REMOVED: i32 = 4;
+const WAIT_DAEMON_LOST: i32 = 5;
+const WAIT_TIMED_OUT: i32 = 6;
+
+async fn wait_nag(
+    client: &mut Client,
+    target: &str,
+    timeout: Option<std::time::Duration>,
+) -> Result<(), ClientError> {
+    let id = match target.parse() {
+        Ok(id) => id,
+        Err(_) => resolve_name(client, target).await?,
+    };
 
-    match response {
-        Response::Ok => Ok(Vec::new()),
-        Response::NagList { nags } => Ok(nags),
-        Response::Error { code, msg } => {
-            Err(Box::<dyn std::error::Error + Sync + Send>::from(format!(
-                "Error! {} ({:?})",
-                msg.as_deref().unwrap_or("No Message"),
-                code
-            )))
+    let waited = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, client.wait(id)).await {
+            Ok(waited) => waited,
+            Err(_) => {
+                eprintln!("Timed out waiting on nag {}", id);
+                std::process::exit(WAIT_TIMED_OUT);
+            }
+        },
+        None => client.wait(id).await,
+    };
+
+    let event = match waited {
+        Ok(event) => event,
+        Err(err) if err.is_disconnected() => {
+            eprintln!("Lost nagd while waiting on nag {}", id);
+            std::process::exit(WAIT_DAEMON_LOST);
         }
+        Err(err) => return Err(err),
+    };
+
+    println!("{} {}", event.kind, event.nag.name);
+    match event.kind {
+        EventKind::Dismissed => std::process::exit(WAIT_DISMISSED),
+        EventKind::Removed => std::process::exit(WAIT_REMOVED),
+        _ => Ok(()),
     }
 }
 
 // ----------------------------------------------------------------------------
 
-async fn edit_nags() {
+/// Finds the one pending or alerting nag whose name contains `name`.
+async fn resolve_name(client: &mut Client, name: &str) -> Result<u64, ClientError> {
+    let filter = NagFilter {
+        name: Some(name.to_string()),
+        ..Default::default()
+    };
+    let mut ids: Vec<u64> = client
+        .list(filter)
+        .await?
+        .iter()
+        .map(|nag| nag.id)
+        .collect();
+    ids.extend(
+        client
+            .alerts()
+            .await?
+            .iter()
+            .filter(|alert| alert.nag.name.to_lowercase().contains(&name.to_lowercase()))
+            .map(|alert| alert.nag.id),
+    );
+
+    let msg = match ids.as_slice() {
+        [id] => return Ok(*id),
+        [] => format!("no nag matches {}", name),
+        _ => format!("{} nags match {}, wait on an id instead", ids.len(), name),
+    };
+    Err(ClientError::Daemon {
+        code: ErrorCode::NotFound,
+        msg: Some(msg),
+    })
+}
+
+// ----------------------------------------------------------------------------
+
+async fn edit_nags(client: &mut Client) -> Result<(), ClientError> {
     // fetch all nags
-    let nags = fetch_nags().await.expect("failed to fetch nags");
+    let nags = client.list(NagFilter::default()).await?;
 
     // write all nags to a temporary file converting the first column
     // from a utc time stamp to a duration string from now()
@@ -195,17 +912,37 @@ async fn edit_nags() {
 
     if !status.success() {
         eprintln!("something went wrong with nvim");
-        return;
+        return Ok(());
     }
 
     // read the nags back in from the temp file
-    let new_nags = read_nags_from_file(&mut temp_file).expect("failed to read nags");
+    let mut new_nags = read_nags_from_file(&mut temp_file).expect("failed to read nags");
+
+    // only check the sounds that were edited, so a sound file that went away
+    // doesn't hold up every other edit
+    let old_nags: HashMap<u64, &Nag> = nags.iter().map(|nag| (nag.id, nag)).collect();
+    for nag in &mut new_nags {
+        let unchanged = old_nags.get(&nag.id).is_some_and(|old_nag| {
+            old_nag.sound_file == nag.sound_file
+                && old_nag.escalation.as_ref().map(|e| &e.sound_file)
+                    == nag.escalation.as_ref().map(|e| &e.sound_file)
+        });
+        if unchanged {
+            continue;
+        }
+        if let Err(err) = check_sound_files(nag) {
+            eprintln!("{}, nothing was saved", err);
+            std::process::exit(1);
+        }
+    }
 
     // compare the new nags to the old nags, if they are different, send the new
     // nags to be stored
     if nags != new_nags {
-        send_nags(new_nags).await.expect("failed to send nags");
+        client.set(new_nags).await?;
     } else {
         info!("Nags ARE the same, nothing to do");
     }
+
+    Ok(())
 }
@@ -0,0 +1,351 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use chrono::Utc;
+use common::{Client, ClientError, Nag, NagEdit, NagFilter, Priority};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::cmp::Reverse;
+use std::io;
+use tokio::time::{interval, Duration};
+use tokio_stream::StreamExt;
+
+// nag tui ////////////////////////////////////////////////////////////////////
+
+const HELP: &str = "a add  e rename  t tags  s snooze  d delete  r refresh  q quit";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Add,
+    Rename,
+    Retag,
+    Snooze,
+}
+
+// ----------------------------------------------------------------------------
+
+impl Action {
+    fn label(self) -> &'static str {
+        match self {
+            Action::Add => "Add (duration name)",
+            Action::Rename => "Rename",
+            Action::Retag => "Tags (comma separated)",
+            Action::Snooze => "Snooze for",
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+// prompts hold on to the nag they were opened for, refreshes keep reordering
+// the list underneath them
+enum Mode {
+    Browse,
+    Prompt {
+        action: Action,
+        target: Option<u64>,
+        input: String,
+    },
+    ConfirmDelete {
+        id: u64,
+        name: String,
+    },
+}
+
+// ----------------------------------------------------------------------------
+
+struct App {
+    nags: Vec<Nag>,
+    table: TableState,
+    mode: Mode,
+    status: Option<String>,
+    connected: bool,
+    quit: bool,
+}
+
+// ----------------------------------------------------------------------------
+
+pub async fn run(client: &mut Client) -> Result<(), ClientError> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, client).await;
+    ratatui::restore();
+
+    result.map_err(|err| ClientError::Transport(err.into()))
+}
+
+// ----------------------------------------------------------------------------
+
+async fn event_loop(terminal: &mut DefaultTerminal, client: &mut Client) -> io::Result<()> {
+    let mut app = App {
+        nags: Vec::new(),
+        table: TableState::default().with_selected(0),
+        mode: Mode::Browse,
+        status: None,
+        connected: true,
+        quit: false,
+    };
+    let mut events = EventStream::new();
+    // refreshes the list and, with it, every countdown
+    let mut tick = interval(Duration::from_secs(1));
+
+    while !app.quit {
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        tokio::select! {
+            _ = tick.tick() => app.refresh(client).await,
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    app.handle_key(key, client).await;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+                None => break,
+            },
+        }
+    }
+
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+
+impl App {
+    fn selected(&self) -> Option<&Nag> {
+        self.table.selected().and_then(|index| self.nags.get(index))
+    }
+
+    async fn refresh(&mut self, client: &mut Client) {
+        let selected_id = self.selected().map(|nag| nag.id);
+        match client.list(NagFilter::default()).await {
+            Ok(mut nags) => {
+                nags.sort_by_key(|nag| (Reverse(nag.priority), nag.end_time));
+                self.nags = nags;
+                if !self.connected {
+                    self.status = Some("Reconnected to nagd".to_string());
+                }
+                self.connected = true;
+            }
+            // keep showing what we had until nagd is back
+            Err(err) => {
+                self.connected = false;
+                self.status = Some(format!("nagd unreachable, retrying: {}", err));
+            }
+        }
+
+        // stay on the same nag wherever it went, or near where it was
+        let last = self.nags.len().saturating_sub(1);
+        let index = selected_id
+            .and_then(|id| self.nags.iter().position(|nag| nag.id == id))
+            .unwrap_or_else(|| self.table.selected().unwrap_or(0).min(last));
+        self.table.select(Some(index));
+    }
+
+    async fn handle_key(&mut self, key: KeyEvent, client: &mut Client) {
+        match &mut self.mode {
+            Mode::Browse => self.browse_key(key, client).await,
+            Mode::Prompt {
+                action,
+                target,
+                input,
+            } => match key.code {
+                KeyCode::Enter => {
+                    let (action, target, input) = (*action, *target, std::mem::take(input));
+                    self.mode = Mode::Browse;
+                    self.status = Some(match apply(action, target, &input, client).await {
+                        Ok(status) => status,
+                        Err(err) => format!("Error! {}", err),
+                    });
+                    self.refresh(client).await;
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            },
+            Mode::ConfirmDelete { id, .. } => {
+                let id = *id;
+                self.mode = Mode::Browse;
+                if key.code == KeyCode::Char('y') {
+                    self.status = Some(match delete(id, client).await {
+                        Ok(status) => status,
+                        Err(err) => format!("Error! {}", err),
+                    });
+                    self.refresh(client).await;
+                }
+            }
+        }
+    }
+
+    async fn browse_key(&mut self, key: KeyEvent, client: &mut Client) {
+        let prompt = |action, target, input: String| Mode::Prompt {
+            action,
+            target,
+            input,
+        };
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Char('r') => self.refresh(client).await,
+            KeyCode::Char('a') => self.mode = prompt(Action::Add, None, String::new()),
+            KeyCode::Char('e') => {
+                if let Some(nag) = self.selected() {
+                    self.mode = prompt(Action::Rename, Some(nag.id), nag.name.clone());
+                }
+            }
+            KeyCode::Char('t') => {
+                if let Some(nag) = self.selected() {
+                    self.mode = prompt(Action::Retag, Some(nag.id), nag.tags.join(", "));
+                }
+            }
+            KeyCode::Char('s') => {
+                if let Some(nag) = self.selected() {
+                    self.mode = prompt(Action::Snooze, Some(nag.id), "5m".to_string());
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(nag) = self.selected() {
+                    self.mode = Mode::ConfirmDelete {
+                        id: nag.id,
+                        name: nag.name.clone(),
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn apply(
+    action: Action,
+    target: Option<u64>,
+    input: &str,
+    client: &mut Client,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    if action == Action::Add {
+        let (duration, name) = input
+            .trim()
+            .split_once(' ')
+            .ok_or("expected a duration and a name, eg: 10m tea")?;
+        let nag = Nag {
+            end_time: Utc::now() + duration_str::parse(duration)?,
+            name: name.trim().to_string(),
+            ..Default::default()
+        };
+        client.add(nag).await?;
+        return Ok(format!("Added {}", name.trim()));
+    }
+
+    let id = target.ok_or("no nag selected")?;
+
+    // only what changed goes to nagd, which edits its own current copy
+    let edit = match action {
+        Action::Rename => NagEdit {
+            name: Some(input.trim().to_string()),
+            ..Default::default()
+        },
+        Action::Retag => NagEdit {
+            tags: Some(
+                input
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            ..Default::default()
+        },
+        Action::Snooze => NagEdit {
+            snooze_seconds: Some(duration_str::parse(input.trim())?.as_secs()),
+            ..Default::default()
+        },
+        Action::Add => unreachable!("handled above"),
+    };
+
+    let nag = client.edit(id, edit).await?;
+    Ok(format!("Updated {}", nag.name))
+}
+
+// ----------------------------------------------------------------------------
+
+async fn delete(id: u64, client: &mut Client) -> Result<String, ClientError> {
+    let filter = NagFilter {
+        ids: vec![id],
+        ..Default::default()
+    };
+
+    let removed = client.remove(filter).await?;
+    Ok(format!("Removed {} nag(s)", removed.len()))
+}
+
+// drawing ////////////////////////////////////////////////////////////////////
+
+fn priority_style(priority: Priority) -> Style {
+    match priority {
+        Priority::Low => Style::new().add_modifier(Modifier::DIM),
+        Priority::Normal => Style::new(),
+        Priority::High => Style::new().fg(Color::Yellow),
+        Priority::Critical => Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, footer] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+
+    let rows = app.nags.iter().map(|nag| {
+        Row::new(vec![
+            nag.id.to_string(),
+            nag.priority.to_string(),
+            if nag.is_paused() {
+                format!("{} paused", nag.remaining())
+            } else {
+                nag.remaining()
+            },
+            nag.name.clone(),
+            nag.tags.join(", "),
+        ])
+        .style(priority_style(nag.priority))
+    });
+    let widths = [
+        Constraint::Length(5),
+        Constraint::Length(9),
+        Constraint::Length(16),
+        Constraint::Fill(3),
+        Constraint::Fill(1),
+    ];
+
+    let title = if app.connected {
+        format!(" nag, {} nag(s) ", app.nags.len())
+    } else {
+        " nag, nagd unreachable ".to_string()
+    };
+    let mut table = Table::new(rows, widths)
+        .header(Row::new(["id", "priority", "in", "name", "tags"]).bold())
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    if !app.connected {
+        table = table.dim();
+    }
+    frame.render_stateful_widget(table, main, &mut app.table);
+
+    let line = match &app.mode {
+        Mode::Browse => app.status.clone().unwrap_or_else(|| HELP.to_string()),
+        Mode::Prompt { action, input, .. } => format!("{}: {}_", action.label(), input),
+        Mode::ConfirmDelete { name, .. } => format!("Delete {}? (y/n)", name),
+    };
+    frame.render_widget(Paragraph::new(line), footer);
+}
@@ -0,0 +1,197 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::{history, session};
+use chrono::Utc;
+
+use common::{Alert, Escalation, EventKind, Nag, CONFIG};
+use log::info;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::process::Command as Proc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
+
+// Alerts /////////////////////////////////////////////////////////////////////
+
+/// Nags that have fired and not been dismissed yet, keyed by nag id.
+pub type Alerts = Arc<Mutex<HashMap<u64, Alert>>>;
+
+// ----------------------------------------------------------------------------
+
+/// Marks the nag as fired, done while the nag list is still locked so a nag is
+/// always found either pending or alerting.
+pub fn raise(nag: &Nag, alerts: &mut HashMap<u64, Alert>) {
+    history::record(EventKind::Fired, nag);
+    alerts.insert(
+        nag.id,
+        Alert {
+            nag: nag.clone(),
+            fired_at: Utc::now(),
+            escalations: 0,
+        },
+    );
+}
+
+// ----------------------------------------------------------------------------
+
+/// Shows the nag until it is dismissed, `silent` leaves out its sound.
+pub async fn trigger_nag(nag: Nag, alerts: Alerts, silent: bool) {
+    let priority = CONFIG.priority(nag.priority);
+    let escalation = nag
+        .escalation
+        .clone()
+        .or_else(|| priority.escalation.clone());
+
+    let mut escalations = 0;
+    loop {
+        let nag_tool = tool_for(nag.nag_tool.as_deref(), CONFIG.nag_tool_for(nag.priority));
+        let mut nagbar = match session::apply(&mut Proc::new(&nag_tool[0]))
+            .args(&nag_tool[1..])
+            .arg(&nag.name)
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("Failed to execute nag_tool with error {}", e);
+                // nothing is left to dismiss it, so nags waiting on that go ahead
+                history::record(EventKind::Dismissed, &nag);
+                break;
+            }
+        };
+
+        let escalated = escalation.as_ref().filter(|_| escalations > 0);
+        let audio = if silent {
+            None
+        } else {
+            start_sound(&nag, escalated)
+        };
+
+        // only wait with a deadline while there are escalations left
+        let dismissed = match escalation.as_ref().filter(|e| escalations < e.max_count) {
+            Some(e) => {
+                let interval = Duration::from_secs(e.interval_minutes.saturating_mul(60));
+                timeout(interval, nagbar.wait()).await.is_ok()
+            }
+            None => {
+                let _ = nagbar.wait().await;
+                true
+            }
+        };
+
+        if let Some(audio) = audio {
+            // the audio child is killed when the task drops it
+            audio.abort();
+        } else if !silent {
+            info!("No paplay");
+        }
+
+        if dismissed {
+            history::record(EventKind::Dismissed, &nag);
+            break;
+        }
+
+        escalations += 1;
+        info!(
+            "Nag {} was not dismissed, escalating ({escalations})",
+            nag.id
+        );
+        if let Err(e) = nagbar.kill().await {
+            eprintln!("Failed to kill nag_tool child with error {}", e);
+        }
+        if let Some(alert) = alerts.lock().await.get_mut(&nag.id) {
+            alert.escalations = escalations;
+        }
+        if let Some(tool) = &priority.escalation_tool {
+            let mut proc = Proc::new(&tool[0]);
+            if let Err(e) = session::apply(&mut proc)
+                .args(&tool[1..])
+                .arg(&nag.name)
+                .spawn()
+            {
+                eprintln!("Failed to execute escalation_tool with error {}", e);
+            }
+        }
+    }
+
+    alerts.lock().await.remove(&nag.id);
+}
+
+// ----------------------------------------------------------------------------
+
+/// The command the nag picked from `[tools]`, `default` when it didn't pick
+/// one or the config no longer has it.
+fn tool_for(name: Option<&str>, default: &'static [String]) -> &'static [String] {
+    let Some(name) = name else {
+        return default;
+    };
+    CONFIG.tool(name).unwrap_or_else(|| {
+        eprintln!("No tool called {} in the config, using the default", name);
+        default
+    })
+}
+
+// ----------------------------------------------------------------------------
+
+fn start_sound(nag: &Nag, escalated: Option<&Escalation>) -> Option<JoinHandle<()>> {
+    let priority = CONFIG.priority(nag.priority);
+    let mut sound_file = nag
+        .sound_file
+        .clone()
+        .or_else(|| priority.sound_file.clone());
+    let mut repeat = priority.sound_repeat.unwrap_or(1);
+
+    if let Some(escalation) = escalated {
+        sound_file = escalation.sound_file.clone().or(sound_file);
+        if escalation.loop_sound {
+            repeat = 0;
+        }
+    }
+
+    // sounds from the config are only ever checked here
+    let sfx_file = match CONFIG.resolve_sound_file(&sound_file?) {
+        Ok(sound) => sound.path,
+        Err(err) => {
+            eprintln!("Not playing a sound for nag {}: {}", nag.id, err);
+            return None;
+        }
+    };
+    Some(tokio::spawn(play_sound(
+        tool_for(
+            nag.audio_tool.as_deref(),
+            CONFIG.audio_tool_for(nag.priority),
+        ),
+        sfx_file,
+        repeat,
+    )))
+}
+
+// ----------------------------------------------------------------------------
+
+async fn play_sound(audio_tool: &'static [String], sound_file: String, repeat: u32) {
+    let mut played = 0;
+    while repeat == 0 || played < repeat {
+        let mut proc = Proc::new(&audio_tool[0]);
+        session::apply(&mut proc)
+            .args(&audio_tool[1..])
+            .arg(&sound_file)
+            .kill_on_drop(true);
+
+        match proc.status().await {
+            Ok(status) if !status.success() => {
+                eprintln!("audio_tool failed on {} with {}", sound_file, status);
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Failed to execute audio_tool with error {}", e);
+                return;
+            }
+        }
+        played += 1;
+    }
+}
@@ -0,0 +1,425 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::alert::Alerts;
+use crate::{history, NagList};
+use chrono::{DateTime, Utc};
+use common::{Alert, Anchor, EventKind, HistoryEvent, Nag, MAX_OFFSET_SECONDS};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::broadcast::error::RecvError;
+
+// checks /////////////////////////////////////////////////////////////////////
+
+/// Whether the nag's parent is still around to be timed from.
+pub fn check_parent(nag: &Nag, nags: &[Nag], alerts: &HashMap<u64, Alert>) -> Result<(), String> {
+    let Some(depends_on) = &nag.depends_on else {
+        return Ok(());
+    };
+    if depends_on.offset_seconds > MAX_OFFSET_SECONDS {
+        return Err(format!(
+            "{} is timed too far from nag {}",
+            nag.name, depends_on.parent
+        ));
+    }
+
+    let pending = nags.iter().any(|other| other.id == depends_on.parent);
+    let alerting = alerts.contains_key(&depends_on.parent);
+    if pending || (depends_on.anchor == Anchor::Dismissed && alerting) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} depends on nag {}, which is not pending",
+            nag.name, depends_on.parent
+        ))
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// `ids` along with every nag timed back from one of them, which are paused
+/// and resumed together so none fires while its parent is frozen.
+pub fn with_before_children(mut ids: HashSet<u64>, nags: &[Nag]) -> HashSet<u64> {
+    loop {
+        let children: Vec<u64> = nags
+            .iter()
+            .filter(|nag| {
+                !ids.contains(&nag.id)
+                    && nag.depends_on.as_ref().is_some_and(|depends_on| {
+                        depends_on.anchor == Anchor::Before && ids.contains(&depends_on.parent)
+                    })
+            })
+            .map(|nag| nag.id)
+            .collect();
+        if children.is_empty() {
+            return ids;
+        }
+        ids.extend(children);
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Times every dependent nag from its parent, so they follow parents that are
+/// edited or snoozed.  Nags still waiting on their parent get an estimate.
+pub fn sync(nags: &mut [Nag]) {
+    // one pass per generation, cycles were turned away before getting here
+    for _ in 0..nags.len() {
+        let end_times: HashMap<u64, DateTime<Utc>> =
+            nags.iter().map(|nag| (nag.id, nag.end_time)).collect();
+
+        let mut changed = false;
+        for nag in nags.iter_mut() {
+            let Some(depends_on) = &nag.depends_on else {
+                continue;
+            };
+            let Some(parent_end_time) = end_times.get(&depends_on.parent) else {
+                continue;
+            };
+
+            let Some(end_time) = depends_on.end_time(*parent_end_time) else {
+                continue;
+            };
+            if nag.end_time != end_time {
+                nag.end_time = end_time;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+// resolving //////////////////////////////////////////////////////////////////
+
+/// Times waiting nags once their parent fires or is dismissed, and takes them
+/// away with their parent.
+pub async fn drive(nags: NagList, alerts: Alerts) {
+    let mut events = history::subscribe();
+
+    loop {
+        match events.recv().await {
+            Ok(event) => resolve(event, &nags).await,
+            Err(RecvError::Lagged(skipped)) => {
+                eprintln!("Dependent nags missed {} history events", skipped);
+                let removed = history::removed_ids().await;
+                let mut nags = nags.lock().await;
+                rescan(&mut nags, &*alerts.lock().await, &removed, Utc::now());
+            }
+            Err(RecvError::Closed) => unreachable!("the history sender is never dropped"),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn resolve(event: HistoryEvent, nags: &NagList) {
+    let parent = event.nag.id;
+    let is_child = |nag: &Nag| {
+        nag.depends_on
+            .as_ref()
+            .is_some_and(|depends_on| depends_on.parent == parent)
+    };
+
+    let mut nags = nags.lock().await;
+    match event.kind {
+        // each removal is an event of its own, which takes the grandchildren
+        EventKind::Removed => nags.retain(|nag| {
+            if is_child(nag) {
+                history::record(EventKind::Removed, nag);
+                false
+            } else {
+                true
+            }
+        }),
+        EventKind::Fired | EventKind::Dismissed => {
+            for nag in nags.iter_mut().filter(|nag| is_child(nag)) {
+                let Some(depends_on) = nag.depends_on.take_if(|depends_on| {
+                    matches!(
+                        (depends_on.anchor, event.kind),
+                        (Anchor::Fired, EventKind::Fired)
+                            | (Anchor::Dismissed, EventKind::Dismissed)
+                    )
+                }) else {
+                    continue;
+                };
+
+                nag.end_time = depends_on
+                    .end_time(event.time)
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC);
+                history::record(EventKind::Edited, nag);
+            }
+            sync(&mut nags);
+        }
+        _ => {}
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Catches up with parents whose events were missed, nags of a `removed`
+/// parent are taken away with it, those waiting on one that is alerting are
+/// timed from when it fired and those whose parent fired and is gone from
+/// `now`.
+pub fn rescan(
+    nags: &mut Vec<Nag>,
+    alerts: &HashMap<u64, Alert>,
+    removed: &HashSet<u64>,
+    now: DateTime<Utc>,
+) {
+    // one pass per generation, so grandchildren go along with their parent
+    let mut removed = removed.clone();
+    loop {
+        let pending: HashSet<u64> = nags.iter().map(|nag| nag.id).collect();
+        let before = nags.len();
+        nags.retain(|nag| match &nag.depends_on {
+            Some(depends_on)
+                if removed.contains(&depends_on.parent)
+                    && !pending.contains(&depends_on.parent) =>
+            {
+                history::record(EventKind::Removed, nag);
+                removed.insert(nag.id);
+                false
+            }
+            _ => true,
+        });
+        if nags.len() == before {
+            break;
+        }
+    }
+
+    let pending: HashSet<u64> = nags.iter().map(|nag| nag.id).collect();
+    for nag in nags.iter_mut() {
+        let Some(depends_on) = nag.depends_on.as_ref().filter(|dep| dep.waits()) else {
+            continue;
+        };
+        if pending.contains(&depends_on.parent) {
+            continue;
+        }
+        let anchor_time = match (depends_on.anchor, alerts.get(&depends_on.parent)) {
+            (Anchor::Dismissed, Some(_)) => continue,
+            (_, Some(alert)) => alert.fired_at,
+            (_, None) => now,
+        };
+
+        nag.end_time = depends_on
+            .end_time(anchor_time)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        nag.depends_on = None;
+        history::record(EventKind::Edited, nag);
+    }
+    sync(nags);
+}
+
+// tests //////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::isolate;
+    use chrono::Duration;
+    use common::history::{history_path, write_event};
+    use common::{Dependency, HistoryFilter};
+    use std::fs::OpenOptions;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    fn nag(id: u64, depends_on: Option<(u64, Anchor, u64)>) -> Nag {
+        Nag {
+            id,
+            name: format!("nag {}", id),
+            end_time: Utc::now(),
+            depends_on: depends_on.map(|(parent, anchor, offset_seconds)| Dependency {
+                parent,
+                anchor,
+                offset_seconds,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sync_follows_parents() {
+        isolate();
+        let mut nags = vec![
+            nag(1, None),
+            nag(2, Some((1, Anchor::Before, 60))),
+            nag(3, Some((2, Anchor::Fired, 30))),
+            nag(4, Some((1, Anchor::Fired, 60))),
+        ];
+        nags[0].end_time += Duration::hours(1);
+
+        sync(&mut nags);
+        assert_eq!(nags[1].end_time, nags[0].end_time - Duration::seconds(60));
+        assert_eq!(nags[2].end_time, nags[1].end_time + Duration::seconds(30));
+
+        // past the last date there is, left where it was rather than panic
+        nags[0].end_time = DateTime::<Utc>::MAX_UTC;
+        let before = nags[3].end_time;
+        sync(&mut nags);
+        assert_eq!(nags[3].end_time, before);
+    }
+
+    #[test]
+    fn test_check_parent_rejects_huge_offsets() {
+        isolate();
+        let nags = vec![nag(1, None)];
+        let alerts = HashMap::new();
+
+        let child = nag(2, Some((1, Anchor::Fired, MAX_OFFSET_SECONDS)));
+        assert!(check_parent(&child, &nags, &alerts).is_ok());
+        let child = nag(2, Some((1, Anchor::Fired, u64::MAX)));
+        assert!(check_parent(&child, &nags, &alerts).is_err());
+        let orphan = nag(3, Some((9, Anchor::Fired, 0)));
+        assert!(check_parent(&orphan, &nags, &alerts).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_times_children() {
+        isolate();
+        let parent = nag(101, None);
+        let nags: NagList = Arc::new(Mutex::new(vec![
+            nag(102, Some((101, Anchor::Fired, 60))),
+            nag(103, Some((101, Anchor::Dismissed, 60))),
+        ]));
+
+        let fired = HistoryEvent {
+            time: Utc::now(),
+            kind: EventKind::Fired,
+            nag: parent.clone(),
+        };
+        resolve(fired.clone(), &nags).await;
+
+        let nags = nags.lock().await;
+        assert_eq!(nags[0].depends_on, None);
+        assert_eq!(nags[0].end_time, fired.time + Duration::seconds(60));
+        assert!(nags[1].depends_on.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_removes_grandchildren() {
+        isolate();
+        let mut events = history::subscribe();
+        let nags: NagList = Arc::new(Mutex::new(vec![
+            nag(202, Some((201, Anchor::Fired, 0))),
+            nag(203, Some((202, Anchor::Dismissed, 0))),
+            nag(204, None),
+        ]));
+
+        let removed = HistoryEvent {
+            time: Utc::now(),
+            kind: EventKind::Removed,
+            nag: nag(201, None),
+        };
+        resolve(removed, &nags).await;
+
+        // the child's own removal takes the grandchild, as drive would
+        let child_removed = loop {
+            let event = events.recv().await.unwrap();
+            if event.kind == EventKind::Removed && event.nag.id == 202 {
+                break event;
+            }
+        };
+        resolve(child_removed, &nags).await;
+
+        let ids: Vec<u64> = nags.lock().await.iter().map(|nag| nag.id).collect();
+        assert_eq!(ids, vec![204]);
+    }
+
+    #[test]
+    fn test_rescan_catches_up() {
+        isolate();
+        let now = Utc::now();
+        let fired_at = now - Duration::minutes(5);
+        let mut nags = vec![
+            nag(301, None),
+            // waiting on a pending parent
+            nag(302, Some((301, Anchor::Dismissed, 60))),
+            // parent alerting, only the one waiting for it to fire goes ahead
+            nag(303, Some((310, Anchor::Fired, 60))),
+            nag(304, Some((310, Anchor::Dismissed, 60))),
+            // parent fired and dismissed
+            nag(305, Some((320, Anchor::Dismissed, 60))),
+        ];
+        let alerts = HashMap::from([(
+            310,
+            Alert {
+                nag: nag(310, None),
+                fired_at,
+                escalations: 0,
+            },
+        )]);
+
+        rescan(&mut nags, &alerts, &HashSet::new(), now);
+        assert!(nags[1].depends_on.is_some());
+        assert_eq!(nags[2].depends_on, None);
+        assert_eq!(nags[2].end_time, fired_at + Duration::seconds(60));
+        assert!(nags[3].depends_on.is_some());
+        assert_eq!(nags[4].depends_on, None);
+        assert_eq!(nags[4].end_time, now + Duration::seconds(60));
+    }
+    #[test]
+    fn test_rescan_removes_children_of_removed_parents() {
+        isolate();
+        let now = Utc::now();
+        let mut nags = vec![
+            nag(401, None),
+            nag(402, Some((401, Anchor::Fired, 60))),
+            nag(403, Some((402, Anchor::Before, 60))),
+            nag(404, Some((410, Anchor::Dismissed, 60))),
+        ];
+
+        // removed while nobody was listening
+        let parent = nags.remove(0);
+        history::record(EventKind::Removed, &parent);
+
+        rescan(&mut nags, &HashMap::new(), &HashSet::from([parent.id]), now);
+        let ids: Vec<u64> = nags.iter().map(|nag| nag.id).collect();
+        assert_eq!(ids, vec![404]);
+        assert_eq!(nags[0].end_time, now + Duration::seconds(60));
+
+        let removed: Vec<u64> = history::load(&HistoryFilter::default())
+            .unwrap()
+            .into_iter()
+            .filter(|event| event.kind == EventKind::Removed)
+            .map(|event| event.nag.id)
+            .collect();
+        assert!(removed.contains(&402) && removed.contains(&403));
+    }
+    #[tokio::test]
+    async fn test_rescan_ignores_removals_from_earlier_runs() {
+        isolate();
+        // an earlier nagd handed out the same id and removed that nag
+        let old = HistoryEvent {
+            time: Utc::now() - Duration::days(1),
+            kind: EventKind::Removed,
+            nag: nag(501, None),
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(history_path())
+            .unwrap();
+        write_event(&old, &mut file).unwrap();
+
+        let fired_at = Utc::now() - Duration::minutes(1);
+        let alerts = HashMap::from([(
+            501,
+            Alert {
+                nag: nag(501, None),
+                fired_at,
+                escalations: 0,
+            },
+        )]);
+        let mut nags = vec![nag(502, Some((501, Anchor::Fired, 60)))];
+
+        let removed = history::removed_ids().await;
+        assert!(!removed.contains(&501));
+        rescan(&mut nags, &alerts, &removed, Utc::now());
+        assert_eq!(nags.len(), 1);
+        assert_eq!(nags[0].end_time, fired_at + Duration::seconds(60));
+    }
+}
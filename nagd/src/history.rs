@@ -0,0 +1,137 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use chrono::{DateTime, Duration, Utc};
+use common::history::{history_path, read_events, write_event};
+use common::{EventKind, HistoryEvent, HistoryFilter, Nag, CONFIG};
+use log::info;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader};
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::broadcast;
+
+// history store //////////////////////////////////////////////////////////////
+
+// appends and pruning both go through this, pruning rewrites the whole file
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+// every recorded event is also sent live to whoever is subscribed
+static EVENTS: LazyLock<broadcast::Sender<HistoryEvent>> =
+    LazyLock::new(|| broadcast::channel(64).0);
+
+// ids start over with every run, so older events may name another nag
+static STARTED: LazyLock<DateTime<Utc>> = LazyLock::new(Utc::now);
+
+// ----------------------------------------------------------------------------
+
+/// Marks the start of this run, before anything is recorded.
+pub fn start() {
+    LazyLock::force(&STARTED);
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn subscribe() -> broadcast::Receiver<HistoryEvent> {
+    EVENTS.subscribe()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn record(kind: EventKind, nag: &Nag) {
+    let event = HistoryEvent {
+        time: Utc::now(),
+        kind,
+        nag: nag.clone(),
+    };
+
+    let _guard = HISTORY_LOCK.lock().expect("history lock poisoned");
+    let path = history_path();
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| write_event(&event, &mut file));
+
+    if let Err(e) = result {
+        eprintln!("Failed to record {} event with error {}", kind, e);
+    }
+
+    // only fails when nobody is listening
+    let _ = EVENTS.send(event);
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn load(filter: &HistoryFilter) -> io::Result<Vec<HistoryEvent>> {
+    let _guard = HISTORY_LOCK.lock().expect("history lock poisoned");
+    let path = history_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let events = read_events(BufReader::new(File::open(&path)?))?;
+    Ok(events
+        .into_iter()
+        .filter(|event| filter.matches(event))
+        .collect())
+}
+
+// ----------------------------------------------------------------------------
+
+/// Ids of every nag removed during this run, which tells a nag that is gone
+/// for good from one that fired.
+pub async fn removed_ids() -> HashSet<u64> {
+    let filter = HistoryFilter {
+        since: Some(*STARTED),
+        ..Default::default()
+    };
+    tokio::task::spawn_blocking(move || load(&filter))
+        .await
+        .expect("history load panicked")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|event| event.kind == EventKind::Removed)
+        .map(|event| event.nag.id)
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn prune() -> io::Result<()> {
+    let days = CONFIG.history_retention_days;
+    if days == 0 {
+        return Ok(());
+    }
+
+    let _guard = HISTORY_LOCK.lock().expect("history lock poisoned");
+    let path = history_path();
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let cutoff = Utc::now() - Duration::days(days as i64);
+    let events = read_events(BufReader::new(File::open(&path)?))?;
+    let total = events.len();
+    let kept: Vec<HistoryEvent> = events
+        .into_iter()
+        .filter(|event| event.time >= cutoff)
+        .collect();
+
+    if kept.len() == total {
+        return Ok(());
+    }
+
+    // write next to the history and swap it in so a crash can't truncate it
+    let temp_path = path.with_extension("jsonl.tmp");
+    let mut file = File::create(&temp_path)?;
+    for event in &kept {
+        write_event(event, &mut file)?;
+    }
+    fs::rename(&temp_path, &path)?;
+
+    info!("Pruned {} history events", total - kept.len());
+    Ok(())
+}
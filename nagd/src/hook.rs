@@ -0,0 +1,203 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::{history, session};
+use common::{HistoryEvent, HistoryFilter, CONFIG};
+use log::info;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as Proc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Semaphore;
+use tokio::time::{timeout, Duration};
+
+// hooks //////////////////////////////////////////////////////////////////////
+
+/// Runs the configured hooks for every history event.  Hooks run on their own
+/// tasks, so a slow or failing hook never holds up nags firing.
+pub async fn drive() {
+    let mut events = history::subscribe();
+    let slots = Arc::new(Semaphore::new(CONFIG.hooks.max_concurrent.max(1)));
+    let mut missed = 0;
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                missed += skipped;
+                continue;
+            }
+            Err(RecvError::Closed) => unreachable!("the history sender is never dropped"),
+        };
+
+        if missed > 0 {
+            let load = || history::load(&HistoryFilter::default());
+            let history = tokio::task::spawn_blocking(load)
+                .await
+                .expect("history load panicked")
+                .unwrap_or_default();
+            let replayed = missed_events(&history, missed, &event);
+            eprintln!(
+                "Hooks missed {} history events, replaying {} from the history",
+                missed,
+                replayed.len()
+            );
+            for event in replayed {
+                dispatch(event.clone(), &slots);
+            }
+            missed = 0;
+        }
+
+        dispatch(event, &slots);
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn dispatch(event: HistoryEvent, slots: &Arc<Semaphore>) {
+    let event = Arc::new(event);
+    for command in CONFIG.hooks.for_event(event.kind) {
+        let slots = Arc::clone(slots);
+        let event = Arc::clone(&event);
+        tokio::spawn(async move {
+            let _slot = slots.acquire_owned().await.expect("hook semaphore closed");
+            run(command, &event).await;
+        });
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// The `skipped` events recorded right before `next`, the history file has
+/// them in the order they were sent.
+fn missed_events<'a>(
+    history: &'a [HistoryEvent],
+    skipped: u64,
+    next: &HistoryEvent,
+) -> &'a [HistoryEvent] {
+    let end = history
+        .iter()
+        .rposition(|event| {
+            event.time == next.time && event.kind == next.kind && event.nag.id == next.nag.id
+        })
+        .unwrap_or(history.len());
+    let start = end.saturating_sub(usize::try_from(skipped).unwrap_or(usize::MAX));
+    &history[start..end]
+}
+
+// ----------------------------------------------------------------------------
+
+async fn run(command: &[String], event: &HistoryEvent) {
+    let nag = &event.nag;
+    let mut proc = Proc::new(&command[0]);
+    session::apply(&mut proc)
+        .args(&command[1..])
+        .env("NAG_EVENT", event.kind.to_string())
+        .env("NAG_ID", nag.id.to_string())
+        .env("NAG_NAME", &nag.name)
+        .env("NAG_END_TIME", nag.end_time.to_rfc3339())
+        .env("NAG_PRIORITY", nag.priority.to_string())
+        .env("NAG_TAGS", nag.tags.join(","))
+        .env(
+            "NAG_SOUND_FILE",
+            nag.sound_file.as_deref().unwrap_or_default(),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .process_group(0)
+        .kill_on_drop(true);
+
+    let mut child = match proc.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!(
+                "Failed to execute {} hook {} with error {}",
+                event.kind, command[0], e
+            );
+            return;
+        }
+    };
+
+    // the hook is free to ignore its stdin, so feeding it counts against the
+    // timeout too, and it's closed before waiting so the hook sees the end
+    let stdin = child.stdin.take();
+    let finished = async {
+        if let Some(mut stdin) = stdin {
+            let json = serde_json::to_vec(event).expect("history events always serialize");
+            let _ = stdin.write_all(&json).await;
+        }
+        child.wait().await
+    };
+
+    let limit = Duration::from_secs(CONFIG.hooks.timeout_seconds);
+    match timeout(limit, finished).await {
+        Ok(Ok(status)) if status.success() => {
+            info!("Ran {} hook {} for nag {}", event.kind, command[0], nag.id);
+        }
+        Ok(Ok(status)) => {
+            eprintln!("{} hook {} failed with {}", event.kind, command[0], status);
+        }
+        Ok(Err(e)) => {
+            eprintln!(
+                "Failed to wait on {} hook {} with error {}",
+                event.kind, command[0], e
+            );
+        }
+        Err(_) => {
+            eprintln!(
+                "{} hook {} timed out after {}s, killing it",
+                event.kind, command[0], CONFIG.hooks.timeout_seconds
+            );
+            // a hook leads its own group, so whatever it started goes too
+            if let Some(pid) = child.id() {
+                // SAFETY: kill has no preconditions, the group is the hook's own
+                unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+            }
+            if let Err(e) = child.kill().await {
+                eprintln!("Failed to kill {} hook with error {}", event.kind, e);
+            }
+        }
+    }
+}
+
+// tests //////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, Utc};
+    use common::{EventKind, Nag};
+
+    fn event(kind: EventKind, id: u64, minutes: i64) -> HistoryEvent {
+        HistoryEvent {
+            time: Utc::now() + ChronoDuration::minutes(minutes),
+            kind,
+            nag: Nag {
+                id,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_missed_events() {
+        let history = vec![
+            event(EventKind::Created, 1, 0),
+            event(EventKind::Fired, 1, 1),
+            event(EventKind::Fired, 2, 2),
+            event(EventKind::Dismissed, 1, 3),
+            event(EventKind::Removed, 2, 4),
+        ];
+
+        assert_eq!(missed_events(&history, 3, &history[4]), &history[1..4]);
+        assert_eq!(missed_events(&history, 10, &history[1]), &history[..1]);
+        assert_eq!(
+            missed_events(&history, 2, &event(EventKind::Edited, 3, 5)),
+            &history[3..]
+        );
+        assert!(missed_events(&[], 2, &history[0]).is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::alert::Alerts;
+use crate::{dispatch, history, NagList, Peer};
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response as HttpResponse};
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use common::{tokens_match, Command, ErrorCode, HistoryFilter, Nag, NagFilter, Response, CONFIG};
+use log::info;
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+// http api ///////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+struct ApiState {
+    nags: NagList,
+    alerts: Alerts,
+}
+
+// ----------------------------------------------------------------------------
+
+/// Query string of `GET /nags`, lists are comma separated.
+#[derive(Deserialize)]
+struct NagQuery {
+    ids: Option<String>,
+    tags: Option<String>,
+    name: Option<String>,
+    regex: Option<String>,
+    after: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+}
+
+// ----------------------------------------------------------------------------
+
+impl NagQuery {
+    fn into_filter(self) -> Result<NagFilter, String> {
+        let ids = split_list(self.ids.as_deref())
+            .map(|id| id.parse().map_err(|e| format!("bad id '{}': {}", id, e)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(NagFilter {
+            ids,
+            tags: split_list(self.tags.as_deref())
+                .map(str::to_string)
+                .collect(),
+            name: self.name,
+            name_regex: self.regex,
+            after: self.after,
+            before: self.before,
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+    list.unwrap_or_default()
+        .split(',')
+        .filter(|item| !item.is_empty())
+}
+
+// server /////////////////////////////////////////////////////////////////////
+
+pub async fn serve_http(nags: NagList, alerts: Alerts) {
+    let Some(addr) = &CONFIG.http.listen else {
+        return;
+    };
+    let Some(token) = &CONFIG.http.token else {
+        eprintln!("http.listen is set without an http.token, not serving on {addr}");
+        return;
+    };
+
+    let router = router(token, nags, alerts);
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to serve the HTTP API on {} with error {}", addr, e);
+            return;
+        }
+    };
+    info!("Serving the HTTP API on {}", addr);
+
+    if let Err(e) = axum::serve(listener, router).await {
+        eprintln!("HTTP API failed with error {}", e);
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn router(token: &'static str, nags: NagList, alerts: Alerts) -> Router {
+    Router::new()
+        .route("/nags", get(list_nags).post(add_nag).put(set_nags))
+        .route("/nags/:id", delete(remove_nag))
+        .route("/alerts", get(list_alerts))
+        .route("/history", get(list_history))
+        .route("/events", get(events))
+        .layer(middleware::from_fn_with_state(token, check_token))
+        .with_state(ApiState { nags, alerts })
+}
+
+// ----------------------------------------------------------------------------
+
+async fn check_token(
+    State(token): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> HttpResponse {
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if given.is_some_and(|given| tokens_match(given, token)) {
+        return next.run(request).await;
+    }
+
+    reply(Response::Error {
+        code: ErrorCode::PermissionDenied,
+        msg: Some("invalid token".to_string()),
+    })
+}
+
+// ----------------------------------------------------------------------------
+
+fn reply(response: Response) -> HttpResponse {
+    let status = match &response {
+        Response::Error {
+            code: ErrorCode::PermissionDenied,
+            ..
+        } => StatusCode::UNAUTHORIZED,
+        Response::Error {
+            code: ErrorCode::NotImplemented,
+            ..
+        } => StatusCode::NOT_IMPLEMENTED,
+        Response::Error {
+            code: ErrorCode::NotFound,
+            ..
+        } => StatusCode::NOT_FOUND,
+        Response::Error { .. } => StatusCode::BAD_REQUEST,
+        _ => StatusCode::OK,
+    };
+
+    (status, Json(response)).into_response()
+}
+
+// ----------------------------------------------------------------------------
+
+async fn run(state: &ApiState, command: Command) -> HttpResponse {
+    reply(dispatch(command, Peer::Other, &state.nags, &state.alerts).await)
+}
+
+// routes /////////////////////////////////////////////////////////////////////
+
+async fn list_nags(State(state): State<ApiState>, Query(query): Query<NagQuery>) -> HttpResponse {
+    match query.into_filter() {
+        Ok(filter) => run(&state, Command::ListNags { filter }).await,
+        Err(msg) => reply(Response::Error {
+            code: ErrorCode::InvalidFormat,
+            msg: Some(msg),
+        }),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn add_nag(State(state): State<ApiState>, Json(nag): Json<Nag>) -> HttpResponse {
+    run(&state, Command::AddNag { nag }).await
+}
+
+// ----------------------------------------------------------------------------
+
+async fn set_nags(State(state): State<ApiState>, Json(nags): Json<Vec<Nag>>) -> HttpResponse {
+    run(&state, Command::SetNags { nags }).await
+}
+
+// ----------------------------------------------------------------------------
+
+async fn remove_nag(State(state): State<ApiState>, Path(id): Path<u64>) -> HttpResponse {
+    let filter = NagFilter {
+        ids: vec![id],
+        ..Default::default()
+    };
+
+    let response = dispatch(
+        Command::RemoveNags { filter },
+        Peer::Other,
+        &state.nags,
+        &state.alerts,
+    )
+    .await;
+    if matches!(&response, Response::NagList { nags } if nags.is_empty()) {
+        return (StatusCode::NOT_FOUND, Json(response)).into_response();
+    }
+    reply(response)
+}
+
+// ----------------------------------------------------------------------------
+
+async fn list_alerts(State(state): State<ApiState>) -> HttpResponse {
+    run(&state, Command::ListAlerts).await
+}
+
+// ----------------------------------------------------------------------------
+
+async fn list_history(
+    State(state): State<ApiState>,
+    Query(filter): Query<HistoryFilter>,
+) -> HttpResponse {
+    run(&state, Command::ListHistory { filter }).await
+}
+
+// ----------------------------------------------------------------------------
+
+/// Streams nag lifecycle events as they are recorded to the history.
+async fn events() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(history::subscribe()).filter_map(|event| {
+        // a client that falls behind just misses the events it lagged on
+        let event = event.ok()?;
+        let data = serde_json::to_string(&event).ok()?;
+        Some(Ok(Event::default()
+            .event(event.kind.to_string())
+            .data(data)))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+// tests //////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::isolate;
+    use axum::body::Body;
+    use axum::http::{Method, Request as HttpRequest};
+    use common::EventKind;
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use tower::ServiceExt;
+
+    const TOKEN: &str = "secret";
+
+    fn test_router() -> Router {
+        isolate();
+        router(TOKEN, Arc::new(Mutex::new(Vec::new())), Alerts::default())
+    }
+
+    fn request(method: Method, uri: &str, body: Option<String>) -> HttpRequest<Body> {
+        let builder = HttpRequest::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {TOKEN}"));
+        match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body)),
+            None => builder.body(Body::empty()),
+        }
+        .expect("bad test request")
+    }
+
+    async fn status(router: &Router, request: HttpRequest<Body>) -> StatusCode {
+        router.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_tokens() {
+        let router = test_router();
+
+        let missing = HttpRequest::get("/nags").body(Body::empty()).unwrap();
+        assert_eq!(status(&router, missing).await, StatusCode::UNAUTHORIZED);
+
+        let wrong = HttpRequest::get("/nags")
+            .header(header::AUTHORIZATION, "Bearer guess")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status(&router, wrong).await, StatusCode::UNAUTHORIZED);
+
+        let unprefixed = HttpRequest::get("/nags")
+            .header(header::AUTHORIZATION, TOKEN)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status(&router, unprefixed).await, StatusCode::UNAUTHORIZED);
+
+        let events = HttpRequest::get("/events").body(Body::empty()).unwrap();
+        assert_eq!(status(&router, events).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_route_status_codes() {
+        let router = test_router();
+        let nag = |id| Nag {
+            id,
+            name: "tea".to_string(),
+            end_time: Utc::now() + chrono::Duration::minutes(5),
+            ..Default::default()
+        };
+
+        let added = request(
+            Method::POST,
+            "/nags",
+            Some(serde_json::to_string(&nag(0)).unwrap()),
+        );
+        assert_eq!(status(&router, added).await, StatusCode::OK);
+
+        let listed = request(Method::GET, "/nags", None);
+        let response = router.clone().oneshot(listed).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let Response::NagList { nags } = serde_json::from_slice(&body).unwrap() else {
+            panic!("expected a nag list");
+        };
+        let id = nags[0].id;
+
+        let ok = [
+            request(Method::GET, &format!("/nags?ids={id}&tags=,"), None),
+            request(Method::GET, "/alerts", None),
+            request(Method::GET, "/history", None),
+            request(Method::DELETE, &format!("/nags/{id}"), None),
+        ];
+        for request in ok {
+            let uri = request.uri().clone();
+            assert_eq!(status(&router, request).await, StatusCode::OK, "{uri}");
+        }
+
+        let huge_id = serde_json::to_string(&vec![nag(u64::MAX)]).unwrap();
+        let invalid = [
+            request(Method::GET, "/nags?ids=tea", None),
+            request(Method::PUT, "/nags", Some(huge_id)),
+        ];
+        for request in invalid {
+            let uri = request.uri().clone();
+            assert_eq!(
+                status(&router, request).await,
+                StatusCode::BAD_REQUEST,
+                "{uri}"
+            );
+        }
+
+        let gone = request(Method::DELETE, &format!("/nags/{id}"), None);
+        assert_eq!(status(&router, gone).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_events_stream() {
+        let router = test_router();
+
+        let response = router
+            .oneshot(request(Method::GET, "/events", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        let nag = Nag {
+            name: "sse test".to_string(),
+            ..Default::default()
+        };
+        history::record(EventKind::Snoozed, &nag);
+
+        // other tests record to the same history, skip past their events
+        let mut body = response.into_body();
+        let found = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(frame) = body.frame().await {
+                let Ok(data) = frame.unwrap().into_data() else {
+                    continue;
+                };
+                let text = String::from_utf8(data.to_vec()).unwrap();
+                if text.contains("sse test") {
+                    return text;
+                }
+            }
+            panic!("event stream ended");
+        })
+        .await
+        .expect("no event on the stream");
+
+        assert!(found.starts_with("event: snoozed\n"), "{found}");
+        assert!(found.contains("data: {"), "{found}");
+    }
+}
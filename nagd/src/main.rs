@@ -3,18 +3,36 @@
 // SPDX-License-Identifier: MIT
 //
 
+mod alert;
+mod dependency;
+mod history;
+mod hook;
+mod http;
+mod quiet;
+mod remote;
+mod routine;
+mod session;
+#[cfg(test)]
+mod testing;
+
+use alert::{raise, trigger_nag, Alerts};
 use chrono::Utc;
 use common::{
-    recv_command, send_response, Command, ErrorCode, Nag, Response, COMSOCK_PATH, CONFIG,
+    find_cycle, recv_command, send_response, Alert, Command, Dependency, ErrorCode, EventKind,
+    HistoryEvent, HistoryFilter, Nag, NagEdit, NagFilter, QuietEffect, Response, COMSOCK_PATH,
+    CONFIG, MAX_MESSAGE_LEN, MAX_REMAINING_SECONDS,
 };
-use log::info;
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::io::BufReader;
-use tokio::net::UnixListener;
-use tokio::process::Command as Proc;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::net::{UnixListener, UnixStream};
 use tokio::spawn;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
 use tokio::time::{interval, Duration};
 
@@ -22,14 +40,102 @@ use tokio::time::{interval, Duration};
 
 type NagList = Arc<Mutex<Vec<Nag>>>;
 
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+// the largest id a client may hand back, nowhere near wrapping NEXT_ID and
+// still exact as a JSON number in any language
+const MAX_ID: u64 = 1 << 53;
+
+// ----------------------------------------------------------------------------
+
+fn assign_ids(new_nags: &mut [Nag]) {
+    // keep ids that came back from the client, bump past them so they are
+    // never handed out again, and number everything else
+    let mut seen = HashSet::new();
+    for nag in new_nags.iter() {
+        NEXT_ID.fetch_max(nag.id.saturating_add(1), Ordering::SeqCst);
+    }
+    for nag in new_nags.iter_mut() {
+        if nag.id == 0 || !seen.insert(nag.id) {
+            nag.id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
 // ensure dir /////////////////////////////////////////////////////////////////
 
 fn ensure_dir(file_path: &str) {
     let path = Path::new(file_path);
-    let parent_dir = path.parent().expect("invalid path");
-    if !parent_dir.exists() {
+    let socket_dir = path.parent().expect("invalid path");
+
+    // other users can only get through to the socket when they are allowed to
+    // connect, the peer credential check in handle_connections does the rest
+    let mode = if CONFIG.allowed_uids.is_empty() {
+        0o700
+    } else {
+        0o711
+    };
+
+    // the directory is nagd's own, so whatever loosened it is undone
+    if socket_dir.exists() {
+        let metadata = fs::metadata(socket_dir).expect("Failed to read socket directory");
+        // SAFETY: getuid has no preconditions and cannot fail
+        if metadata.uid() != unsafe { libc::getuid() } {
+            panic!(
+                "{} belongs to another user, refusing to put the socket in it",
+                socket_dir.display()
+            );
+        }
+
+        let current = metadata.permissions().mode() & 0o777;
+        if current != mode {
+            warn!(
+                "{} is {:o}, setting it to {:o}",
+                socket_dir.display(),
+                current,
+                mode
+            );
+            fs::set_permissions(socket_dir, fs::Permissions::from_mode(mode))
+                .expect("Failed to set socket directory permissions");
+        }
+        return;
+    }
+
+    if let Some(parent_dir) = socket_dir.parent() {
         fs::create_dir_all(parent_dir).expect("Failed to create directory");
     }
+    fs::create_dir(socket_dir).expect("Failed to create socket directory");
+    fs::set_permissions(socket_dir, fs::Permissions::from_mode(mode))
+        .expect("Failed to set socket directory permissions");
+}
+
+// peer credentials ///////////////////////////////////////////////////////////
+
+/// Who is on the other end, only nagd's own user on the local socket gets to
+/// say where the tools nagd starts show up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Peer {
+    Owner,
+    Other,
+}
+
+// ----------------------------------------------------------------------------
+
+fn check_peer(stream: &UnixStream) -> Result<Peer, String> {
+    let uid = stream
+        .peer_cred()
+        .map_err(|e| format!("failed to read peer credentials: {}", e))?
+        .uid();
+
+    // SAFETY: getuid has no preconditions and cannot fail
+    let own_uid = unsafe { libc::getuid() };
+    if uid == own_uid {
+        Ok(Peer::Owner)
+    } else if CONFIG.allowed_uids.contains(&uid) {
+        Ok(Peer::Other)
+    } else {
+        Err(format!("uid {} is not allowed to connect", uid))
+    }
 }
 
 // entry point/////////////////////////////////////////////////////////////////
@@ -39,133 +145,888 @@ async fn main() {
     env_logger::init();
 
     info!("Starting nagd...");
+    history::start();
     ensure_dir(COMSOCK_PATH);
     let nags = NagList::new(Mutex::new(Vec::new()));
+    let alerts = Alerts::default();
+
+    spawn(prune_history());
+    spawn(routine::drive(Arc::clone(&nags), Arc::clone(&alerts)));
+    spawn(dependency::drive(Arc::clone(&nags), Arc::clone(&alerts)));
+    spawn(hook::drive());
+
+    spawn(remote::listen_tcp(Arc::clone(&nags), Arc::clone(&alerts)));
+    spawn(http::serve_http(Arc::clone(&nags), Arc::clone(&alerts)));
 
     let connections_clone = Arc::clone(&nags);
-    spawn(handle_connections(connections_clone));
+    spawn(handle_connections(connections_clone, Arc::clone(&alerts)));
 
     let process_clone = Arc::clone(&nags);
-    process_nags(process_clone).await;
+    process_nags(process_clone, alerts).await;
 }
 
 // ----------------------------------------------------------------------------
 
-async fn process_nags(nags: NagList) {
-    let mut interval = interval(Duration::from_secs(1));
+async fn prune_history() {
+    let mut interval = interval(Duration::from_secs(60 * 60));
 
     loop {
         interval.tick().await;
 
-        let mut nags_lock = nags.lock().await;
-        let now = Utc::now();
-
-        // find all nags that are finished
-        nags_lock.retain(|nag| {
-            if nag.end_time <= now {
-                let nag_clone = nag.clone();
-                tokio::spawn(async move {
-                    trigger_nag(nag_clone).await;
-                });
-                false
-            } else {
-                true
-            }
-        });
+        if let Err(e) = history::prune() {
+            eprintln!("Failed to prune history with error {}", e);
+        }
     }
 }
 
 // ----------------------------------------------------------------------------
 
-async fn trigger_nag(nag: Nag) {
-    let nagbar = tokio::spawn(async move {
-        let mut proc = Proc::new(&CONFIG.nag_tool[0]);
-        for arg in &CONFIG.nag_tool[1..] {
-            proc.arg(arg);
-        }
-        proc.status().await.expect("failed to execute i3-navbar");
-    });
+async fn process_nags(nags: NagList, alerts: Alerts) {
+    let mut interval = interval(Duration::from_secs(1));
 
-    let paplay = nag.sound_file.map(|sfx_file| {
-        let mut proc = Proc::new(&CONFIG.audio_tool[0]);
-        for arg in &CONFIG.audio_tool[1..] {
-            proc.arg(arg);
-        }
-        proc.arg(sfx_file);
+    loop {
+        interval.tick().await;
 
-        proc.spawn().expect("failed to execute audio_tool")
-    });
+        let mut nags_lock = nags.lock().await;
+        let mut alerts_lock = alerts.lock().await;
+        let now = Utc::now();
+        let quiet = quiet::status();
 
-    nagbar.await.expect("nag_tool finished successfully");
-    if let Some(mut child) = paplay {
-        if let Err(e) = child.kill().await {
-            eprintln!("Failed to kill paplay child with error {}", e);
-        }
-    } else {
-        info!("No paplay");
+        // find all nags that are finished, leaving those still waiting on
+        // another nag and those held back while it is quiet
+        nags_lock.retain(|nag| {
+            let waiting = nag.depends_on.as_ref().is_some_and(Dependency::waits);
+            if nag.end_time > now || waiting || nag.is_paused() {
+                return true;
+            }
+
+            let effect = quiet.effect(nag, now);
+            if effect == Some(QuietEffect::Deferred) {
+                return true;
+            }
+
+            raise(nag, &mut alerts_lock);
+            let nag_clone = nag.clone();
+            let alerts_clone = Arc::clone(&alerts);
+            let silent = effect == Some(QuietEffect::Silenced);
+            tokio::spawn(async move {
+                trigger_nag(nag_clone, alerts_clone, silent).await;
+            });
+            false
+        });
     }
 }
 
 // ----------------------------------------------------------------------------
 
-async fn handle_connections(nag_list: NagList) {
+async fn handle_connections(nag_list: NagList, alerts: Alerts) {
     // clean up any existing socket
     let socket_path = COMSOCK_PATH;
     let _ = std::fs::remove_file(socket_path);
 
     let listener = UnixListener::bind(socket_path).expect("failed to bind socket");
+    let mode = if CONFIG.allowed_uids.is_empty() {
+        0o600
+    } else {
+        0o666
+    };
+    fs::set_permissions(socket_path, fs::Permissions::from_mode(mode))
+        .expect("Failed to set socket permissions");
 
     loop {
         let nags = Arc::clone(&nag_list);
+        let alerts = Arc::clone(&alerts);
 
         info!("Socket bound, waiting connection...");
         let (stream, _) = listener.accept().await.expect("Listener failed to accept");
 
+        let peer = check_peer(&stream);
         let (read_stream, mut write_stream) = stream.into_split();
-        let mut reader = BufReader::new(read_stream);
-        info!("Connection joined!  Awaiting command...");
-
-        let response = match recv_command(&mut reader).await {
-            Ok(command) => match command {
-                Command::AddNag { nag } => add_nag(nag, &nags).await,
-                Command::ListNags => list_nags(nags).await,
-                Command::SetNags { nags: new_nags } => set_nags(new_nags, &nags).await,
-            },
-            Err(err) => Response::Error {
-                code: ErrorCode::UnknownCommand,
-                msg: Some(err.to_string()),
-            },
+        let reader = BufReader::new(read_stream);
+
+        spawn(async move {
+            let msg = match peer {
+                Ok(peer) => return handle_stream(reader, write_stream, peer, nags, alerts).await,
+                Err(msg) => msg,
+            };
+
+            // answered without reading anything from a peer that isn't allowed
+            info!("Rejecting connection: {}", msg);
+
+            let response = Response::Error {
+                code: ErrorCode::PermissionDenied,
+                msg: Some(msg),
+            };
+            if let Err(e) = send_response(&mut write_stream, response).await {
+                eprintln!("Failed to send rejection with error {}", e);
+            }
+        });
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Answers commands until the client hangs up, shared by the unix socket and
+/// remote listeners.
+async fn handle_stream<R, W>(
+    mut reader: R,
+    mut write_stream: W,
+    peer: Peer,
+    nags: NagList,
+    alerts: Alerts,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    info!("Connection joined!  Awaiting command...");
+
+    loop {
+        let (response, keep_going) = match recv_command(&mut reader).await {
+            Ok(command) => (dispatch(command, peer, &nags, &alerts).await, true),
+            Err(err) if hung_up(err.as_ref()) => {
+                info!("Connection closed");
+                return;
+            }
+            // the stream can't be trusted to be at a message boundary anymore
+            Err(err) => (
+                Response::Error {
+                    code: ErrorCode::UnknownCommand,
+                    msg: Some(err.to_string()),
+                },
+                false,
+            ),
         };
 
         info!("Sending response...");
-        send_response(&mut write_stream, response)
-            .await
-            .expect("Failed to send response");
+        if let Err(e) = send_response(&mut write_stream, response).await {
+            eprintln!("Failed to send response with error {}", e);
+            return;
+        }
+        if !keep_going {
+            return;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn hung_up(err: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    err.downcast_ref::<std::io::Error>()
+        .is_some_and(|err| err.kind() == std::io::ErrorKind::UnexpectedEof)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Runs a command against the daemon state, whichever front end it came from.
+async fn dispatch(command: Command, peer: Peer, nags: &NagList, alerts: &Alerts) -> Response {
+    match command {
+        Command::AddNag { nag } => add_nag(nag, nags, alerts).await,
+        Command::ListNags { filter } => list_nags(filter, nags).await,
+        Command::SetNags { nags: new_nags } => set_nags(new_nags, nags, alerts).await,
+        Command::RemoveNags { filter } => remove_nags(filter, nags).await,
+        Command::ClearNags => clear_nags(nags).await,
+        Command::EditNag { id, edit } => edit_nag(id, edit, nags).await,
+        Command::PauseNags { filter } => pause_nags(filter, nags).await,
+        Command::ResumeNags { filter } => resume_nags(filter, nags).await,
+        Command::ListAlerts => list_alerts(alerts).await,
+        Command::ListHistory { filter } => list_history(filter),
+        Command::WaitNag { id } => wait_nag(id, nags, alerts).await,
+        Command::StartRoutine { name } => routine::start(name, nags).await,
+        Command::ListRoutines => routine::list(nags).await,
+        Command::SkipRoutine { name } => routine::skip(name, nags).await,
+        Command::StopRoutine { name } => routine::stop(name, nags).await,
+        Command::SetDnd { on, until } => quiet::set_dnd(on, until),
+        Command::GetQuiet => quiet::report(nags).await,
+        Command::SetSessionEnv { env } if peer == Peer::Owner => session::set_env(env),
+        Command::SetSessionEnv { .. } => Response::Error {
+            code: ErrorCode::PermissionDenied,
+            msg: Some("only nagd's own user can set the session environment".to_string()),
+        },
     }
 }
 
 // ----------------------------------------------------------------------------
 
-async fn add_nag(nag: Nag, nags: &NagList) -> Response {
+async fn add_nag(nag: Nag, nags: &NagList, alerts: &Alerts) -> Response {
     let mut nags = nags.lock().await;
-    nags.push(nag);
+    let checked = match check_nag(&nag) {
+        Ok(()) => dependency::check_parent(&nag, &nags, &*alerts.lock().await),
+        err => err,
+    };
+    if let Err(msg) = checked {
+        return Response::Error {
+            code: ErrorCode::InvalidFormat,
+            msg: Some(msg),
+        };
+    }
+
+    push_nag(nag, &mut nags);
+    dependency::sync(&mut nags);
     Response::Ok
 }
 
 // ----------------------------------------------------------------------------
 
-async fn list_nags(nags: NagList) -> Response {
+/// Turns away nags nagd can't run as they are, whichever client sent them.
+fn check_nag(nag: &Nag) -> Result<(), String> {
+    if let Some(escalation) = &nag.escalation {
+        escalation
+            .check()
+            .map_err(|err| format!("{}: {}", nag.name, err))?;
+    }
+    if nag
+        .remaining_seconds
+        .is_some_and(|remaining| remaining > MAX_REMAINING_SECONDS)
+    {
+        return Err(format!("{} is paused with too much time left", nag.name));
+    }
+    CONFIG.check_tools(nag)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Numbers and records a new nag, whether a client or nagd itself added it.
+fn push_nag(mut nag: Nag, nags: &mut Vec<Nag>) -> u64 {
+    nag.id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    history::record(EventKind::Created, &nag);
+    let id = nag.id;
+    nags.push(nag);
+    id
+}
+
+// ----------------------------------------------------------------------------
+
+fn invalid_filter(err: impl std::fmt::Display) -> Response {
+    Response::Error {
+        code: ErrorCode::InvalidFormat,
+        msg: Some(err.to_string()),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn list_nags(filter: NagFilter, nags: &NagList) -> Response {
+    let matcher = match filter.matcher() {
+        Ok(matcher) => matcher,
+        Err(err) => return invalid_filter(err),
+    };
+
     let nags = nags.lock().await;
-    let nags_list = serde_json::to_string(&*nags).unwrap_or_else(|_| "[]".to_string());
+    let nags: Vec<Nag> = nags
+        .iter()
+        .filter(|nag| matcher.matches(nag))
+        .cloned()
+        .collect();
+    let nags_list = serde_json::to_string(&nags).unwrap_or_else(|_| "[]".to_string());
 
     info!("Listing nags... {}", nags_list);
-    Response::NagList { nags: nags.clone() }
+    Response::NagList { nags }
 }
 
 // ----------------------------------------------------------------------------
 
-async fn set_nags(new_nags: Vec<Nag>, nags: &NagList) -> Response {
+async fn set_nags(mut new_nags: Vec<Nag>, nags: &NagList, alerts: &Alerts) -> Response {
+    if let Some(nag) = new_nags.iter().find(|nag| nag.id > MAX_ID) {
+        return Response::Error {
+            code: ErrorCode::InvalidFormat,
+            msg: Some(format!("id {} of {} is too large", nag.id, nag.name)),
+        };
+    }
+
     let mut nags = nags.lock().await;
+    assign_ids(&mut new_nags);
+
+    let checked = match find_cycle(&new_nags) {
+        Some(id) => Err(format!("nag {} ends up depending on itself", id)),
+        None => {
+            let alerts = alerts.lock().await;
+            new_nags.iter().try_for_each(|nag| {
+                check_nag(nag)?;
+                dependency::check_parent(nag, &new_nags, &alerts)
+            })
+        }
+    };
+    if let Err(msg) = checked {
+        return Response::Error {
+            code: ErrorCode::InvalidFormat,
+            msg: Some(msg),
+        };
+    }
+    dependency::sync(&mut new_nags);
+
+    let old_nags: HashMap<u64, &Nag> = nags.iter().map(|nag| (nag.id, nag)).collect();
+    for nag in &new_nags {
+        match old_nags.get(&nag.id) {
+            None => history::record(EventKind::Created, nag),
+            Some(old_nag) if is_snooze(old_nag, nag) => history::record(EventKind::Snoozed, nag),
+            Some(old_nag) if *old_nag != nag => history::record(EventKind::Edited, nag),
+            Some(_) => {}
+        }
+    }
+    let new_ids: HashSet<u64> = new_nags.iter().map(|nag| nag.id).collect();
+    for nag in nags.iter().filter(|nag| !new_ids.contains(&nag.id)) {
+        history::record(EventKind::Removed, nag);
+    }
+
     *nags = new_nags;
     Response::Ok
 }
+
+// ----------------------------------------------------------------------------
+
+/// Edits the nag nagd has now, so nothing that happened to the others since the
+/// client last listed them is undone.
+async fn edit_nag(id: u64, edit: NagEdit, nags: &NagList) -> Response {
+    let invalid = |msg: String| Response::Error {
+        code: ErrorCode::InvalidFormat,
+        msg: Some(msg),
+    };
+
+    let mut nags = nags.lock().await;
+    let Some(nag) = nags.iter_mut().find(|nag| nag.id == id) else {
+        return Response::Error {
+            code: ErrorCode::NotFound,
+            msg: Some(format!("no pending nag with id {}", id)),
+        };
+    };
+
+    let mut edited = nag.clone();
+    if let Some(seconds) = edit.snooze_seconds {
+        if let Some(depends_on) = &edited.depends_on {
+            return invalid(format!(
+                "{} is timed from nag {}",
+                edited.name, depends_on.parent
+            ));
+        }
+        if let Err(msg) = edited.snooze(seconds, Utc::now()) {
+            return invalid(msg);
+        }
+    }
+    let only_snoozed = edit.name.is_none() && edit.tags.is_none();
+    if let Some(name) = edit.name {
+        edited.name = name;
+    }
+    if let Some(tags) = edit.tags {
+        edited.tags = tags;
+    }
+
+    if edited != *nag {
+        let kind = if only_snoozed {
+            EventKind::Snoozed
+        } else {
+            EventKind::Edited
+        };
+        history::record(kind, &edited);
+        *nag = edited.clone();
+        // nags timed from this one move along with it
+        dependency::sync(&mut nags);
+    }
+
+    info!("Edited nag {:?}", edited);
+    Response::NagList { nags: vec![edited] }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Whether the only change is the nag being pushed back.
+fn is_snooze(old_nag: &Nag, nag: &Nag) -> bool {
+    nag.end_time > old_nag.end_time
+        && *old_nag
+            == Nag {
+                end_time: old_nag.end_time,
+                ..nag.clone()
+            }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn remove_nags(filter: NagFilter, nags: &NagList) -> Response {
+    if filter.is_empty() {
+        return Response::Error {
+            code: ErrorCode::InvalidFormat,
+            msg: Some("refusing to remove with an empty filter, use ClearNags".to_string()),
+        };
+    }
+
+    let matcher = match filter.matcher() {
+        Ok(matcher) => matcher,
+        Err(err) => return invalid_filter(err),
+    };
+
+    let mut nags = nags.lock().await;
+    let (removed, kept) = nags.drain(..).partition(|nag| matcher.matches(nag));
+    *nags = kept;
+
+    for nag in &removed {
+        history::record(EventKind::Removed, nag);
+    }
+    info!("Removed nags {:?}", removed);
+    Response::NagList { nags: removed }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn clear_nags(nags: &NagList) -> Response {
+    let mut nags = nags.lock().await;
+    let removed = std::mem::take(&mut *nags);
+
+    for nag in &removed {
+        history::record(EventKind::Removed, nag);
+    }
+    info!("Cleared nags {:?}", removed);
+    Response::NagList { nags: removed }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn pause_nags(filter: NagFilter, nags: &NagList) -> Response {
+    let matcher = match filter.matcher() {
+        Ok(matcher) => matcher,
+        Err(err) => return invalid_filter(err),
+    };
+
+    // nags still waiting on another nag have no countdown to freeze
+    let now = Utc::now();
+    let mut nags = nags.lock().await;
+    let matched = nags
+        .iter()
+        .filter(|nag| {
+            let waiting = nag.depends_on.as_ref().is_some_and(Dependency::waits);
+            !nag.is_paused() && !waiting && matcher.matches(nag)
+        })
+        .map(|nag| nag.id)
+        .collect();
+    let ids = dependency::with_before_children(matched, &nags);
+
+    let mut paused = Vec::new();
+    for nag in nags
+        .iter_mut()
+        .filter(|nag| !nag.is_paused() && ids.contains(&nag.id))
+    {
+        nag.pause(now);
+        history::record(EventKind::Edited, nag);
+        paused.push(nag.clone());
+    }
+
+    info!("Paused nags {:?}", paused);
+    Response::NagList { nags: paused }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn resume_nags(filter: NagFilter, nags: &NagList) -> Response {
+    let matcher = match filter.matcher() {
+        Ok(matcher) => matcher,
+        Err(err) => return invalid_filter(err),
+    };
+
+    let now = Utc::now();
+    let mut nags = nags.lock().await;
+    let matched = nags
+        .iter()
+        .filter(|nag| nag.is_paused() && matcher.matches(nag))
+        .map(|nag| nag.id)
+        .collect();
+    let ids = dependency::with_before_children(matched, &nags);
+
+    let mut resumed = Vec::new();
+    for nag in nags
+        .iter_mut()
+        .filter(|nag| nag.is_paused() && ids.contains(&nag.id))
+    {
+        nag.resume(now);
+        history::record(EventKind::Edited, nag);
+        resumed.push(nag.clone());
+    }
+    // nags timed from a resumed nag move along with it
+    dependency::sync(&mut nags);
+
+    info!("Resumed nags {:?}", resumed);
+    Response::NagList { nags: resumed }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn list_alerts(alerts: &Alerts) -> Response {
+    let alerts = alerts.lock().await;
+    let mut alerts: Vec<Alert> = alerts.values().cloned().collect();
+    alerts.sort_by_key(|alert| alert.fired_at);
+
+    Response::AlertList { alerts }
+}
+
+// ----------------------------------------------------------------------------
+
+fn list_history(filter: HistoryFilter) -> Response {
+    let mut events = match history::load(&filter) {
+        Ok(events) => events,
+        Err(err) => {
+            return Response::Error {
+                code: ErrorCode::InvalidFormat,
+                msg: Some(format!("failed to read history: {}", err)),
+            }
+        }
+    };
+    if let Some(limit) = filter.limit() {
+        events.drain(..events.len().saturating_sub(limit));
+    }
+
+    // a reply too big for nag to read is no use, so the oldest make way
+    let mut budget = MAX_MESSAGE_LEN as usize - 1024;
+    let fit = events
+        .iter()
+        .rev()
+        .take_while(|event| {
+            let len = serde_json::to_string(event).map_or(usize::MAX, |json| json.len() + 1);
+            budget = budget.saturating_sub(len);
+            budget > 0
+        })
+        .count();
+    events.drain(..events.len() - fit);
+
+    Response::History { events }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn wait_nag(id: u64, nags: &NagList, alerts: &Alerts) -> Response {
+    // subscribe before looking so nothing can happen to the nag in between
+    let mut events = history::subscribe();
+    let since = Utc::now();
+    {
+        let nags = nags.lock().await;
+        let alerts = alerts.lock().await;
+        if !nags.iter().any(|nag| nag.id == id) && !alerts.contains_key(&id) {
+            return Response::Error {
+                code: ErrorCode::NotFound,
+                msg: Some(format!("no pending or alerting nag with id {}", id)),
+            };
+        }
+    }
+
+    info!("Waiting on nag {}...", id);
+    let ends_wait = |event: &HistoryEvent| {
+        event.nag.id == id
+            && matches!(
+                event.kind,
+                EventKind::Fired | EventKind::Dismissed | EventKind::Removed
+            )
+    };
+    loop {
+        match events.recv().await {
+            Ok(event) if ends_wait(&event) => {
+                return Response::NagEvent {
+                    event: Box::new(event),
+                }
+            }
+            Ok(_) => {}
+            // the event may have been among the skipped ones, which the
+            // history file still has
+            Err(RecvError::Lagged(skipped)) => {
+                info!("Wait on nag {} lagged by {} events", id, skipped);
+                let filter = HistoryFilter {
+                    since: Some(since),
+                    ..Default::default()
+                };
+                let missed = history::load(&filter)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|event| ends_wait(event));
+                if let Some(event) = missed {
+                    return Response::NagEvent {
+                        event: Box::new(event),
+                    };
+                }
+            }
+            Err(RecvError::Closed) => unreachable!("the history sender is never dropped"),
+        }
+    }
+}
+
+// tests //////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::isolate;
+    use common::{Escalation, HISTORY_LIMIT};
+    use std::collections::BTreeMap;
+
+    fn is_invalid(response: &Response) -> bool {
+        matches!(
+            response,
+            Response::Error {
+                code: ErrorCode::InvalidFormat,
+                ..
+            }
+        )
+    }
+
+    #[tokio::test]
+    async fn test_edit_nag_in_place() {
+        isolate();
+        let end_time = Utc::now() + chrono::Duration::minutes(5);
+        let nags: NagList = Arc::new(Mutex::new(vec![
+            Nag {
+                id: 1,
+                name: "tea".to_string(),
+                end_time,
+                ..Default::default()
+            },
+            Nag {
+                id: 2,
+                name: "coffee".to_string(),
+                end_time,
+                ..Default::default()
+            },
+        ]));
+        let alerts = Alerts::default();
+        let edit = |id, edit| dispatch(Command::EditNag { id, edit }, Peer::Owner, &nags, &alerts);
+
+        let renamed = NagEdit {
+            name: Some("green tea".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(edit(1, renamed).await, Response::NagList { .. }));
+        let snoozed = NagEdit {
+            snooze_seconds: Some(60),
+            ..Default::default()
+        };
+        assert!(matches!(edit(2, snoozed).await, Response::NagList { .. }));
+        {
+            let nags = nags.lock().await;
+            assert_eq!(nags[0].name, "green tea");
+            assert_eq!(nags[0].end_time, end_time);
+            assert_eq!(nags[1].end_time, end_time + chrono::Duration::minutes(1));
+        }
+
+        let forever = NagEdit {
+            snooze_seconds: Some(u64::MAX),
+            ..Default::default()
+        };
+        assert!(is_invalid(&edit(2, forever).await));
+        assert!(matches!(
+            edit(9, NagEdit::default()).await,
+            Response::Error {
+                code: ErrorCode::NotFound,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pause_takes_before_children_along() {
+        isolate();
+        let end_time = Utc::now() + chrono::Duration::hours(1);
+        let nags: NagList = Arc::new(Mutex::new(vec![
+            Nag {
+                id: 1,
+                name: "flight".to_string(),
+                end_time,
+                ..Default::default()
+            },
+            Nag {
+                id: 2,
+                name: "pack".to_string(),
+                end_time: end_time - chrono::Duration::minutes(30),
+                depends_on: Some(Dependency {
+                    parent: 1,
+                    anchor: common::Anchor::Before,
+                    offset_seconds: 30 * 60,
+                }),
+                ..Default::default()
+            },
+        ]));
+        let alerts = Alerts::default();
+        let filter = NagFilter {
+            ids: vec![1],
+            ..Default::default()
+        };
+
+        let command = Command::PauseNags {
+            filter: filter.clone(),
+        };
+        dispatch(command, Peer::Owner, &nags, &alerts).await;
+        assert!(nags.lock().await.iter().all(Nag::is_paused));
+
+        let command = Command::ResumeNags { filter };
+        dispatch(command, Peer::Owner, &nags, &alerts).await;
+        let nags = nags.lock().await;
+        assert!(!nags.iter().any(Nag::is_paused));
+        assert_eq!(
+            nags[1].end_time,
+            nags[0].end_time - chrono::Duration::minutes(30)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_huge_remaining_seconds() {
+        isolate();
+        let nags = NagList::default();
+        let alerts = Alerts::default();
+        let nag = Nag {
+            name: "forever".to_string(),
+            remaining_seconds: Some(u64::MAX),
+            ..Default::default()
+        };
+
+        let response = dispatch(Command::AddNag { nag }, Peer::Owner, &nags, &alerts).await;
+        assert!(is_invalid(&response));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_huge_ids() {
+        isolate();
+        let nags = NagList::default();
+        let alerts = Alerts::default();
+        let nag = Nag {
+            id: u64::MAX,
+            name: "far out".to_string(),
+            ..Default::default()
+        };
+
+        let response = dispatch(
+            Command::SetNags { nags: vec![nag] },
+            Peer::Owner,
+            &nags,
+            &alerts,
+        )
+        .await;
+        assert!(is_invalid(&response));
+        assert!(NEXT_ID.load(Ordering::SeqCst) <= MAX_ID + 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_zero_escalation_interval() {
+        isolate();
+        let nags = NagList::default();
+        let alerts = Alerts::default();
+        let nag = Nag {
+            name: "stand up".to_string(),
+            escalation: Some(Escalation {
+                interval_minutes: 0,
+                max_count: 3,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let response = dispatch(
+            Command::AddNag { nag: nag.clone() },
+            Peer::Owner,
+            &nags,
+            &alerts,
+        )
+        .await;
+        assert!(is_invalid(&response));
+        let response = dispatch(
+            Command::SetNags { nags: vec![nag] },
+            Peer::Owner,
+            &nags,
+            &alerts,
+        )
+        .await;
+        assert!(is_invalid(&response));
+        assert!(nags.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unlisted_tools() {
+        isolate();
+        let nags = NagList::default();
+        let alerts = Alerts::default();
+        let listed = Nag {
+            name: "stretch".to_string(),
+            end_time: Utc::now() + chrono::Duration::minutes(5),
+            nag_tool: Some("notify".to_string()),
+            ..Default::default()
+        };
+        let unlisted = [
+            Nag {
+                nag_tool: Some("rm".to_string()),
+                ..listed.clone()
+            },
+            Nag {
+                audio_tool: Some("rm".to_string()),
+                ..listed.clone()
+            },
+        ];
+
+        for nag in &unlisted {
+            let response = dispatch(
+                Command::AddNag { nag: nag.clone() },
+                Peer::Owner,
+                &nags,
+                &alerts,
+            )
+            .await;
+            assert!(is_invalid(&response));
+        }
+        let response = dispatch(
+            Command::SetNags {
+                nags: [listed.clone()].into_iter().chain(unlisted).collect(),
+            },
+            Peer::Owner,
+            &nags,
+            &alerts,
+        )
+        .await;
+        assert!(is_invalid(&response));
+        assert!(nags.lock().await.is_empty());
+
+        let response = dispatch(Command::AddNag { nag: listed }, Peer::Owner, &nags, &alerts).await;
+        assert!(matches!(response, Response::Ok));
+        assert_eq!(nags.lock().await.len(), 1);
+    }
+
+    #[test]
+    fn test_list_history_limit() {
+        isolate();
+        let since = Utc::now();
+        let nag = Nag {
+            name: "history limit".to_string(),
+            ..Default::default()
+        };
+        for _ in 0..HISTORY_LIMIT + 5 {
+            history::record(EventKind::Edited, &nag);
+        }
+
+        let Response::History { events } = list_history(HistoryFilter::default()) else {
+            panic!("expected the history");
+        };
+        assert_eq!(events.len(), HISTORY_LIMIT);
+
+        let filter = HistoryFilter {
+            since: Some(since),
+            name: Some("history limit".to_string()),
+            ..Default::default()
+        };
+        let Response::History { events } = list_history(filter) else {
+            panic!("expected the history");
+        };
+        assert_eq!(events.len(), HISTORY_LIMIT + 5);
+    }
+
+    #[tokio::test]
+    async fn test_session_env_only_from_owner() {
+        isolate();
+        let nags = NagList::default();
+        let alerts = Alerts::default();
+        let command = Command::SetSessionEnv {
+            env: BTreeMap::from([("DISPLAY".to_string(), ":9".to_string())]),
+        };
+
+        let response = dispatch(command.clone(), Peer::Other, &nags, &alerts).await;
+        assert!(matches!(
+            response,
+            Response::Error {
+                code: ErrorCode::PermissionDenied,
+                ..
+            }
+        ));
+        assert_eq!(
+            dispatch(command, Peer::Owner, &nags, &alerts).await,
+            Response::Ok
+        );
+    }
+}
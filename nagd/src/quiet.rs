@@ -0,0 +1,119 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::NagList;
+use chrono::{DateTime, Utc};
+use common::{ErrorCode, QuietStatus, Response, CONFIG};
+use log::info;
+use std::sync::Mutex;
+
+// do not disturb /////////////////////////////////////////////////////////////
+
+// on, and when it turns itself off again, forgotten when nagd restarts
+static DND: Mutex<(bool, Option<DateTime<Utc>>)> = Mutex::new((false, None));
+
+// ----------------------------------------------------------------------------
+
+pub fn status() -> QuietStatus {
+    let (dnd, dnd_until) = *DND.lock().expect("dnd lock poisoned");
+    let status = QuietStatus {
+        dnd,
+        dnd_until,
+        quiet: CONFIG.quiet.clone(),
+        ..Default::default()
+    };
+
+    // report do not disturb as off once it has run out
+    QuietStatus {
+        dnd: status.is_dnd(Utc::now()),
+        ..status
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// The status along with what it means for each pending nag, worked out here
+/// since quiet hours are in nagd's local time rather than the client's.
+pub async fn report(nags: &NagList) -> Response {
+    let now = Utc::now();
+    let mut status = status();
+    status.quiet_now = status.is_quiet(now);
+    status.effects = nags
+        .lock()
+        .await
+        .iter()
+        .filter(|nag| !nag.is_paused())
+        .filter_map(|nag| {
+            let effect = status.effect(nag, nag.end_time.max(now))?;
+            Some((nag.id, effect))
+        })
+        .collect();
+
+    Response::Quiet { status }
+}
+
+// ----------------------------------------------------------------------------
+
+pub fn set_dnd(on: bool, until: Option<DateTime<Utc>>) -> Response {
+    if on && until.is_some_and(|until| until <= Utc::now()) {
+        return Response::Error {
+            code: ErrorCode::InvalidFormat,
+            msg: Some("do not disturb would already be over".to_string()),
+        };
+    }
+
+    let until = until.filter(|_| on);
+    *DND.lock().expect("dnd lock poisoned") = (on, until);
+    match (on, until) {
+        (true, Some(until)) => info!("Do not disturb until {}", until),
+        (true, None) => info!("Do not disturb"),
+        (false, _) => info!("Do not disturb off"),
+    }
+
+    Response::Ok
+}
+
+// tests //////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::isolate;
+    use common::{Nag, QuietEffect};
+    use std::sync::Arc;
+    use tokio::sync::Mutex as AsyncMutex;
+
+    #[tokio::test]
+    async fn test_report_effects() {
+        isolate();
+        let nags: NagList = Arc::new(AsyncMutex::new(vec![
+            Nag {
+                id: 1,
+                end_time: Utc::now(),
+                ..Default::default()
+            },
+            Nag {
+                id: 2,
+                end_time: Utc::now(),
+                remaining_seconds: Some(60),
+                ..Default::default()
+            },
+        ]));
+
+        assert_eq!(set_dnd(true, None), Response::Ok);
+        let report = report(&nags).await;
+        assert_eq!(set_dnd(false, None), Response::Ok);
+
+        let Response::Quiet { status } = report else {
+            panic!("unexpected response {:?}", report);
+        };
+        assert!(status.quiet_now);
+        // paused nags aren't coming due at all
+        assert_eq!(
+            status.effects.into_iter().collect::<Vec<_>>(),
+            vec![(1, QuietEffect::Deferred)]
+        );
+    }
+}
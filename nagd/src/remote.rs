@@ -0,0 +1,97 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::alert::Alerts;
+use crate::{handle_stream, NagList, Peer};
+
+use common::remote::split_boxed;
+use common::{accept_handshake, CONFIG};
+use log::info;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::spawn;
+use tokio::time::{timeout, Duration};
+
+// remote listener ////////////////////////////////////////////////////////////
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// ----------------------------------------------------------------------------
+
+pub async fn listen_tcp(nag_list: NagList, alerts: Alerts) {
+    let remote = &CONFIG.remote;
+    let Some(addr) = &remote.listen else {
+        return;
+    };
+    let Some(token) = &remote.token else {
+        eprintln!("remote.listen is set without a remote.token, not listening on {addr}");
+        return;
+    };
+
+    let acceptor = match (&remote.tls_cert, &remote.tls_key) {
+        (Some(cert), Some(key)) => match common::tls::acceptor(cert, key) {
+            Ok(acceptor) => Some(acceptor),
+            Err(e) => {
+                eprintln!("Failed to load tls certificate with error {}", e);
+                return;
+            }
+        },
+        (None, None) => None,
+        _ => {
+            eprintln!("remote.tls_cert and remote.tls_key have to be set together");
+            return;
+        }
+    };
+
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on {} with error {}", addr, e);
+            return;
+        }
+    };
+    info!("Listening for remote connections on {}", addr);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("Failed to accept remote connection with error {}", e);
+                continue;
+            }
+        };
+
+        let nags = Arc::clone(&nag_list);
+        let alerts = Arc::clone(&alerts);
+        let acceptor = acceptor.clone();
+        spawn(async move {
+            info!("Remote connection from {}", peer);
+            // TLS and the token both have to be done in time, a client that
+            // stalls either holds on to a task and a socket
+            let connect = async {
+                let (mut reader, mut writer) = match acceptor {
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(stream) => split_boxed(stream),
+                        Err(e) => return Err(format!("TLS handshake failed: {}", e)),
+                    },
+                    None => split_boxed(stream),
+                };
+                match accept_handshake(&mut reader, &mut writer, token).await {
+                    Ok(accepted) => Ok((accepted, reader, writer)),
+                    Err(e) => Err(format!("handshake failed: {}", e)),
+                }
+            };
+
+            match timeout(HANDSHAKE_TIMEOUT, connect).await {
+                Ok(Ok((true, reader, writer))) => {
+                    handle_stream(reader, writer, Peer::Other, nags, alerts).await
+                }
+                Ok(Ok((false, ..))) => info!("Rejected {}: invalid token", peer),
+                Ok(Err(e)) => info!("Connection from {}: {}", peer, e),
+                Err(_) => info!("Handshake with {} timed out", peer),
+            }
+        });
+    }
+}
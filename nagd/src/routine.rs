@@ -0,0 +1,313 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use crate::alert::Alerts;
+use crate::{history, push_nag, NagList};
+use chrono::{DateTime, Utc};
+use common::{
+    Advance, ErrorCode, EventKind, HistoryEvent, Nag, Response, Routine, RoutineStatus, CONFIG,
+};
+use log::info;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+
+// running routines ///////////////////////////////////////////////////////////
+
+struct Running {
+    routine: Routine,
+    step: usize,
+    cycle: u32,
+    /// only the current step is an actual nag
+    nag_id: u64,
+}
+
+// keyed by routine name, so each routine runs at most once
+static RUNNING: LazyLock<Mutex<HashMap<String, Running>>> = LazyLock::new(Default::default);
+
+// ----------------------------------------------------------------------------
+
+impl Running {
+    fn schedule(&mut self, name: &str, from: DateTime<Utc>, nags: &mut Vec<Nag>) {
+        let mut nag = self.routine.steps[self.step].nag(from);
+        nag.tags.push(name.to_string());
+        self.nag_id = push_nag(nag, nags);
+    }
+
+    /// Moves on to the next step, false once a routine that doesn't repeat is
+    /// done.
+    fn advance(&mut self) -> bool {
+        self.step += 1;
+        if self.step < self.routine.steps.len() {
+            return true;
+        }
+        if !self.routine.repeat {
+            return false;
+        }
+
+        self.step = 0;
+        self.cycle += 1;
+        true
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn remove_nag(id: u64, nags: &mut Vec<Nag>) {
+    if let Some(index) = nags.iter().position(|nag| nag.id == id) {
+        history::record(EventKind::Removed, &nags.remove(index));
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn not_running(name: &str) -> Response {
+    Response::Error {
+        code: ErrorCode::NotFound,
+        msg: Some(format!("routine {} is not running", name)),
+    }
+}
+
+// commands ///////////////////////////////////////////////////////////////////
+
+pub async fn start(name: String, nags: &NagList) -> Response {
+    let Some(routine) = CONFIG.routine(&name) else {
+        return Response::Error {
+            code: ErrorCode::NotFound,
+            msg: Some(format!("no routine called {}", name)),
+        };
+    };
+    if routine.steps.is_empty() {
+        return Response::Error {
+            code: ErrorCode::InvalidFormat,
+            msg: Some(format!("routine {} has no steps", name)),
+        };
+    }
+
+    let mut running = RUNNING.lock().await;
+    let mut nags = nags.lock().await;
+    if let Some(old) = running.remove(&name) {
+        remove_nag(old.nag_id, &mut nags);
+    }
+
+    let mut started = Running {
+        routine,
+        step: 0,
+        cycle: 0,
+        nag_id: 0,
+    };
+    started.schedule(&name, Utc::now(), &mut nags);
+
+    info!("Started routine {}", name);
+    running.insert(name, started);
+    Response::Ok
+}
+
+// ----------------------------------------------------------------------------
+
+pub async fn list(nags: &NagList) -> Response {
+    let running = RUNNING.lock().await;
+    let nags = nags.lock().await;
+
+    let mut routines: Vec<RoutineStatus> = running
+        .iter()
+        .map(|(name, running)| RoutineStatus {
+            name: name.clone(),
+            step: running.step,
+            steps: running.routine.steps.len(),
+            cycle: running.cycle,
+            nag: nags.iter().find(|nag| nag.id == running.nag_id).cloned(),
+        })
+        .collect();
+    routines.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Response::RoutineList { routines }
+}
+
+// ----------------------------------------------------------------------------
+
+pub async fn skip(name: String, nags: &NagList) -> Response {
+    let mut running = RUNNING.lock().await;
+    let Some(routine) = running.get_mut(&name) else {
+        return not_running(&name);
+    };
+
+    let mut nags = nags.lock().await;
+    remove_nag(routine.nag_id, &mut nags);
+    if routine.advance() {
+        routine.schedule(&name, Utc::now(), &mut nags);
+    } else {
+        info!("Routine {} finished", name);
+        running.remove(&name);
+    }
+
+    Response::Ok
+}
+
+// ----------------------------------------------------------------------------
+
+pub async fn stop(name: String, nags: &NagList) -> Response {
+    let Some(routine) = RUNNING.lock().await.remove(&name) else {
+        return not_running(&name);
+    };
+
+    remove_nag(routine.nag_id, &mut *nags.lock().await);
+    info!("Stopped routine {}", name);
+    Response::Ok
+}
+
+// stepping ///////////////////////////////////////////////////////////////////
+
+/// Moves routines along as their nags fire or are dismissed.
+pub async fn drive(nags: NagList, alerts: Alerts) {
+    let mut events = history::subscribe();
+
+    loop {
+        match events.recv().await {
+            Ok(event) => step(event, &nags).await,
+            Err(RecvError::Lagged(skipped)) => {
+                eprintln!("Routines missed {} history events", skipped);
+                catch_up(&nags, &alerts).await;
+            }
+            Err(RecvError::Closed) => unreachable!("the history sender is never dropped"),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+async fn step(event: HistoryEvent, nags: &NagList) {
+    let mut running = RUNNING.lock().await;
+    let Some((name, routine)) = running
+        .iter_mut()
+        .find(|(_, routine)| routine.nag_id == event.nag.id)
+    else {
+        return;
+    };
+    let name = name.clone();
+
+    let advance = match (event.kind, routine.routine.advance) {
+        (EventKind::Fired, Advance::Fired) | (EventKind::Dismissed, Advance::Dismissed) => true,
+        // taken away from under the routine, eg: by nag rm or nag clear
+        (EventKind::Removed, _) => false,
+        _ => return,
+    };
+
+    if advance && routine.advance() {
+        routine.schedule(&name, event.time, &mut *nags.lock().await);
+        return;
+    }
+
+    info!("Routine {} finished", name);
+    running.remove(&name);
+}
+
+// ----------------------------------------------------------------------------
+
+/// Moves routines along whose nag is no longer where `step` last left it, for
+/// when its events were among the missed ones.
+async fn catch_up(nags: &NagList, alerts: &Alerts) {
+    // the history file still tells a removed nag from a dismissed one
+    let removed = history::removed_ids().await;
+
+    let mut running = RUNNING.lock().await;
+    let mut nags = nags.lock().await;
+    let alerts = alerts.lock().await;
+    let now = Utc::now();
+
+    running.retain(|name, routine| {
+        if nags.iter().any(|nag| nag.id == routine.nag_id) {
+            return true;
+        }
+        let from = match (alerts.get(&routine.nag_id), routine.routine.advance) {
+            (Some(_), Advance::Dismissed) => return true,
+            (Some(alert), Advance::Fired) => alert.fired_at,
+            (None, _) if removed.contains(&routine.nag_id) => {
+                info!("Routine {} finished", name);
+                return false;
+            }
+            (None, _) => now,
+        };
+
+        if routine.advance() {
+            routine.schedule(name, from, &mut nags);
+            return true;
+        }
+        info!("Routine {} finished", name);
+        false
+    });
+}
+
+// tests //////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::isolate;
+    use chrono::Duration;
+    use common::history::{history_path, write_event};
+    use common::{Alert, POMODORO};
+    use std::fs::OpenOptions;
+
+    #[tokio::test]
+    async fn test_catch_up_after_missed_events() {
+        isolate();
+        let nags = NagList::default();
+        let alerts = Alerts::default();
+        assert_eq!(start(POMODORO.to_string(), &nags).await, Response::Ok);
+        let current = |running: &HashMap<String, Running>| running[POMODORO].nag_id;
+        let first = current(&*RUNNING.lock().await);
+
+        // fired without us seeing it, pomodoro waits for it to be dismissed
+        let nag = nags.lock().await.remove(0);
+        alerts.lock().await.insert(
+            nag.id,
+            Alert {
+                nag,
+                fired_at: Utc::now(),
+                escalations: 0,
+            },
+        );
+        catch_up(&nags, &alerts).await;
+        assert_eq!(current(&*RUNNING.lock().await), first);
+
+        // and dismissed without us seeing that either
+        alerts.lock().await.remove(&first);
+        catch_up(&nags, &alerts).await;
+        let running = RUNNING.lock().await;
+        assert_eq!(running[POMODORO].step, 1);
+        assert_ne!(current(&running), first);
+        assert_eq!(nags.lock().await[0].id, current(&running));
+    }
+    #[tokio::test]
+    async fn test_catch_up_ignores_removals_from_earlier_runs() {
+        isolate();
+        let nags = NagList::default();
+        let alerts = Alerts::default();
+        assert_eq!(start("tea".to_string(), &nags).await, Response::Ok);
+        let first = RUNNING.lock().await["tea"].nag_id;
+
+        // an earlier nagd handed out the same id and removed that nag
+        let nag = nags.lock().await.remove(0);
+        let old = HistoryEvent {
+            time: Utc::now() - Duration::days(1),
+            kind: EventKind::Removed,
+            nag,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(history_path())
+            .unwrap();
+        write_event(&old, &mut file).unwrap();
+
+        // fired and dismissed without us seeing it, so tea moves on
+        catch_up(&nags, &alerts).await;
+        let running = RUNNING.lock().await;
+        assert_eq!(running["tea"].step, 1);
+        assert_ne!(running["tea"].nag_id, first);
+    }
+}
@@ -0,0 +1,75 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+use common::{Response, CONFIG};
+use log::info;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tokio::process::Command as Proc;
+
+// session environment ////////////////////////////////////////////////////////
+
+// the latest value of each variable from the sessions nag was run from,
+// nagd's own environment until then
+static SESSION_ENV: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+// ----------------------------------------------------------------------------
+
+pub fn set_env(env: BTreeMap<String, String>) -> Response {
+    // nagd's own config has the last say on what gets through
+    let env = CONFIG.filter_session_env(env);
+
+    // merged rather than replaced, a session that lacks some variable keeps
+    // the one it had before
+    let mut session_env = SESSION_ENV.lock().expect("session env lock poisoned");
+    let before = session_env.clone();
+    session_env.extend(env);
+    if *session_env != before {
+        info!("Session environment is now {:?}", *session_env);
+    }
+
+    Response::Ok
+}
+
+// ----------------------------------------------------------------------------
+
+/// Starts `proc` in the latest session's environment.
+pub fn apply(proc: &mut Proc) -> &mut Proc {
+    proc.envs(
+        SESSION_ENV
+            .lock()
+            .expect("session env lock poisoned")
+            .iter(),
+    )
+}
+
+// tests //////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::isolate;
+
+    fn env(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_set_env_keeps_missing_keys() {
+        isolate();
+        set_env(env(&[
+            ("WAYLAND_DISPLAY", "wayland-1"),
+            ("PULSE_SERVER", "unix:/a"),
+        ]));
+        set_env(env(&[("PULSE_SERVER", "unix:/b"), ("NOT_ALLOWED", "x")]));
+
+        let session_env = SESSION_ENV.lock().unwrap();
+        assert_eq!(session_env["WAYLAND_DISPLAY"], "wayland-1");
+        assert_eq!(session_env["PULSE_SERVER"], "unix:/b");
+        assert!(!session_env.contains_key("NOT_ALLOWED"));
+    }
+}
@@ -0,0 +1,48 @@
+//
+// 2024
+// SPDX-License-Identifier: MIT
+//
+
+//! Keeps tests away from the user's own config and history.
+
+use std::fs;
+use std::sync::Once;
+
+// test setup /////////////////////////////////////////////////////////////////
+
+const CONFIG: &str = r#"
+edit_tool = ["vim"]
+nag_tool = ["true"]
+audio_tool = ["true"]
+
+[tools]
+notify = ["true"]
+
+[[routines.tea.steps]]
+name = "Steeped"
+minutes = 4
+
+[[routines.tea.steps]]
+name = "Drink your tea"
+minutes = 10
+"#;
+
+static ISOLATE: Once = Once::new();
+
+// ----------------------------------------------------------------------------
+
+/// Points the config and data directories at a fresh one for this test run and
+/// starts its history, call it before anything touches `CONFIG` or the history.
+pub fn isolate() {
+    ISOLATE.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("nagd-test-{}", std::process::id()));
+        let config_dir = dir.join("config");
+        fs::create_dir_all(config_dir.join("nag")).expect("failed to create test config dir");
+        fs::write(config_dir.join("nag/config.toml"), CONFIG).expect("failed to write test config");
+
+        std::env::set_var("XDG_CONFIG_HOME", config_dir);
+        std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+        fs::create_dir_all(dir.join("data/nag")).expect("failed to create test data dir");
+    });
+    crate::history::start();
+}